@@ -13,5 +13,5 @@ cd "$(dirname "$0")"
 if [ $do_compile -eq 1 ]; then
     cargo build --release
 else
-    target/release/othello $position $time_limit
+    target/release/othello $position $time_limit --tuple
 fi
\ No newline at end of file
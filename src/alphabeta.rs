@@ -1,202 +1,645 @@
 /// This file implements the logic for generating a recommended
 /// move, given a specific board. The algorithm used to do so is
-/// minimax with alpha-beta pruning. Additionally, an attempt at
-/// basic move-ordering is made along with the utilization of a
-/// HashMap for storing board values.
+/// principal variation search, a variant of alpha-beta pruning in
+/// negamax form: every value is seen from the perspective of the
+/// player to go next, and all moves but the first are searched with
+/// a null window which only proves them worse than the best move so far.
+/// The moves are ordered using the results of previous iterations
+/// stored in a transposition table, along with killer and history
+/// heuristics. Once few enough squares are empty, the endgame solver
+/// takes over and the boards are searched to the end of the game.
 use crate::board::OthelloPosition;
-use crate::evaluator::Evaluator;
-use crate::move_generator::{get_move_from_board_diff, Move};
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
-extern crate crossbeam;
+use crate::endgame::{EndgameSolver, DEFAULT_ENDGAME_EMPTIES};
+use crate::evaluator::{Evaluate, Evaluator, FINAL_DISC_WEIGHT};
+use crate::move_generator::{get_moves, has_moves, Move};
+use crate::move_ordering::MoveOrdering;
+use crate::symmetry::{canonical, inverse, transform_move};
+use crate::time_manager::TimeManager;
+use crate::transposition::{Bound, Entry, TranspositionTable, DEFAULT_TABLE_SIZE};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 pub const VERY_HIGH: isize = 9999999999999;
 pub const VERY_LOW: isize = -VERY_HIGH;
+/// The depth stored in the transposition table for boards solved to the end
+/// of the game, which no depth-limited search can improve upon.
+const SOLVED_DEPTH: u32 = u32::MAX;
+/// The depth limit of the search by default, which in practice leaves
+/// the search to be limited by time alone.
+pub const DEFAULT_MAX_DEPTH: u32 = 100000000;
+/// The heuristic value corresponding to a single piece, a rough scale
+/// used to report heuristic values in pieces.
+const EVAL_UNITS_PER_DISC: f64 = 10_000.0;
+/// The greatest number of moves in a game, which bounds the length of a
+/// principal variation. Every move but a pass fills a square, and a pass
+/// is always followed by a move.
+const MAX_GAME_LENGTH: usize = 120;
 
+/// The settings of a search.
+#[derive(Clone)]
+pub struct SearchConfig {
+    /// The evaluation function used for boards which are not searched any deeper.
+    pub evaluator: Arc<dyn Evaluate + Send + Sync>,
+    /// The number of threads searching in parallel. Defaults to the
+    /// number of cores available.
+    pub threads: usize,
+    /// The number of empty squares at or below which boards are solved
+    /// to the end of the game instead of being evaluated heuristically.
+    pub endgame_empties: u32,
+    /// The number of entries in the transposition table.
+    pub table_size: usize,
+    /// The depth of the last iteration, after which the search stops
+    /// even if time remains.
+    pub max_depth: u32,
+    /// Whether boards are stored in the transposition table under their
+    /// canonical orientation, so that rotations and reflections of a board
+    /// share an entry. This finds more boards in the table at the cost of
+    /// computing the canonical orientation of every board searched.
+    pub canonical_table: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            evaluator: Arc::new(Evaluator::default()),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            endgame_empties: DEFAULT_ENDGAME_EMPTIES,
+            table_size: DEFAULT_TABLE_SIZE,
+            max_depth: DEFAULT_MAX_DEPTH,
+            canonical_table: false,
+        }
+    }
+}
+
+/// The outcome of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// The recommended move, or None if the player to go next has to pass.
+    pub best_move: Option<Move>,
+    /// The value of the board from the perspective of the player to go next,
+    /// according to the deepest completed iteration.
+    pub score: isize,
+    /// The deepest completed iteration, or 0 if none completed.
+    pub depth: u32,
+    /// The expected continuation of the game, starting with the best move,
+    /// as far as it is known from the transposition table.
+    pub pv: Vec<Move>,
+    /// The number of boards searched by all threads together.
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    /// Returns the value in pieces, which is exact for boards solved to
+    /// the end of the game and a rough scaling of heuristic values otherwise.
+    pub fn score_in_discs(&self) -> f64 {
+        if self.score.abs() >= FINAL_DISC_WEIGHT {
+            (self.score / FINAL_DISC_WEIGHT) as f64
+        } else {
+            self.score as f64 / EVAL_UNITS_PER_DISC
+        }
+    }
+}
+
+/// The result of the iterative deepening search of a single thread.
+struct ThreadResult {
+    depth: u32,
+    best_move: Move,
+    score: isize,
+    nodes: u64,
+}
+
+/// The state shared by all nodes searched by a single thread.
+struct SearchContext<'a> {
+    /// The TimeManager keeping track of the time budget of the search.
+    time: &'a TimeManager,
+    /// The transposition table shared by all iterations and threads of the search.
+    table: &'a TranspositionTable,
+    /// The evaluation function used for boards which are not searched any deeper.
+    evaluator: &'a dyn Evaluate,
+    /// The killer moves and history scores collected by the thread.
+    ordering: MoveOrdering,
+    /// The number of empty squares at or below which boards are solved
+    /// to the end of the game instead of being evaluated heuristically.
+    endgame_empties: u32,
+    /// The number of boards searched by the thread so far.
+    nodes: u64,
+    /// Whether boards are stored in the transposition table under their
+    /// canonical orientation.
+    canonical_table: bool,
+}
+
+/// Searches the board and returns the recommended move,
+/// or None if the player to go next has to pass.
+/// See search for how the move is found.
+///
+/// # Arguments
+///
+/// * `board` - The starting board used as the root of the search tree.
+/// * `time` - The TimeManager keeping track of the time budget of the search.
+/// * `config` - The settings of the search.
+pub fn alphabeta_move_gen(
+    board: &OthelloPosition,
+    time: &TimeManager,
+    config: &SearchConfig,
+) -> Option<Move> {
+    search(board, time, config).best_move
+}
 
 /// Utilisies iterative deepening search in order to
 /// inspect and evaluate the potential boards that can
 /// be reached from the given board. Keeps searching until
-/// either a set depth-limit is reached or the parsed time
-/// limit is reached.
-/// 
+/// either a set depth-limit is reached or the soft time
+/// limit is reached, or the iterations are as deep as the number
+/// of empty squares. An iteration interrupted by the hard time limit
+/// only counts if it found a better move than the last completed
+/// iteration before being interrupted. The best move found is returned,
+/// along with its value and the principal variation. The best move is None if the player to go
+/// next has no legal moves and therefore has to pass.
+///
+/// With more than one thread, the search is parallelised using
+/// Lazy SMP: every thread runs its own iterative deepening search
+/// from the board, sharing only the transposition table, so that
+/// each thread benefits from the boards already searched by the
+/// others. Half of the helper threads start one depth ahead of the
+/// main thread to spread the threads over different parts of the tree.
+/// Once the main thread is done, the helper threads are stopped and the
+/// move of the deepest completed iteration of any thread is returned.
+/// The given TimeManager is left running, so it can be used again.
+///
 /// # Arguments
-/// 
+///
 /// * `board` - The starting board used as the root of the search tree.
-/// * `start_time` - The Instant represeting the start of program execution.
-/// * `time_limit` - An integer representing the set time limit in secodns.
-pub fn alphabeta_move_gen(
+/// * `time` - The TimeManager keeping track of the time budget of the search.
+/// * `config` - The settings of the search.
+pub fn search(board: &OthelloPosition, time: &TimeManager, config: &SearchConfig) -> SearchResult {
+    let table = TranspositionTable::new(config.table_size);
+    search_with_table(board, time, config, &table)
+}
+
+/// Searches the board like search, but with a transposition table which
+/// may hold the results of earlier searches and keeps the results of this
+/// one for later searches. The size of the table in the settings is ignored.
+///
+/// # Arguments
+///
+/// * `board` - The starting board used as the root of the search tree.
+/// * `time` - The TimeManager keeping track of the time budget of the search.
+/// * `config` - The settings of the search.
+/// * `table` - The transposition table used by the search.
+pub(crate) fn search_with_table(
     board: &OthelloPosition,
-    start_time: Instant,
-    time_limit: u64,
-) -> Option<Move> {
-    let max_depth = 100000000;
-    let mut depth_limit = 1;
-    let mut best_move = None;
+    time: &TimeManager,
+    config: &SearchConfig,
+    table: &TranspositionTable,
+) -> SearchResult {
+    if !has_moves(board) {
+        return SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            pv: Vec::new(),
+            nodes: 0,
+        };
+    }
+    // The helper threads are stopped through a TimeManager belonging to
+    // this search, since stopping the caller's would end later searches.
+    let time = &time.detached();
+    let results = crossbeam::scope(|scope| {
+        let helpers: Vec<_> = (1..config.threads)
+            .map(|thread| {
+                let start_depth = 1 + thread as u32 % 2;
+                scope.spawn(move |_| iterative_deepening(board, start_depth, time, table, config))
+            })
+            .collect();
+        let mut results = vec![iterative_deepening(board, 1, time, table, config)];
+        time.stop();
+        for helper in helpers {
+            results.push(helper.join().expect("a search thread panicked"));
+        }
+        results
+    })
+    .expect("a search thread panicked");
+
+    let nodes = results.iter().map(|result| result.nodes).sum();
+    // Prefer the main thread, which comes first, among equally deep results.
+    let mut best = &results[0];
+    for result in &results {
+        if result.depth > best.depth {
+            best = result;
+        }
+    }
+    SearchResult {
+        best_move: Some(best.best_move),
+        score: best.score,
+        depth: best.depth,
+        pv: principal_variation(board, best.best_move, table, config.canonical_table),
+        nodes,
+    }
+}
+
+/// Searches every legal move of the board separately, each with a full
+/// window, so that every move gets an exact value rather than only the
+/// best one. The time is split evenly between the moves, and each move is
+/// searched one move shallower than the maximum depth of the settings.
+/// Returns one result per move with the move as the best move, its value
+/// from the perspective of the player to go next in the board, and the
+/// principal variation starting with the move, ordered best first.
+///
+/// # Arguments
+///
+/// * `board` - The board whose moves are searched.
+/// * `time_ms` - The time in milliseconds available for all moves together.
+/// * `config` - The settings of the searches.
+/// * `table` - The transposition table used by the searches.
+pub fn search_moves(
+    board: &OthelloPosition,
+    time_ms: u64,
+    config: &SearchConfig,
+    table: &TranspositionTable,
+) -> Vec<SearchResult> {
+    let moves = get_moves(board);
+    let move_time_ms = time_ms / moves.len().max(1) as u64;
+    let sign = if board.max_player() { 1 } else { -1 };
+    let mut results: Vec<SearchResult> = moves
+        .into_iter()
+        .map(|p_move| {
+            let mut pv = vec![p_move];
+            let mut child = Move::make_move(board, &p_move);
+            // The value of the child is from the perspective of the opponent,
+            // unless the opponent has to pass.
+            let mut child_sign = -1;
+            if !child.is_game_over() && !has_moves(&child) {
+                pv.push(Move::pass(child.to_move()));
+                child = child.pass();
+                child_sign = 1;
+            }
+            if child.is_game_over() {
+                return SearchResult {
+                    best_move: Some(p_move),
+                    score: sign * Evaluator::final_score(&child),
+                    depth: pv.len() as u32,
+                    pv,
+                    nodes: 1,
+                };
+            }
+
+            let moves_played = pv.len() as u32;
+            let mut child_config = config.clone();
+            child_config.max_depth = config.max_depth.saturating_sub(moves_played).max(1);
+            let time = TimeManager::new(Instant::now(), move_time_ms);
+            let result = search_with_table(&child, &time, &child_config, table);
+            pv.extend(result.pv);
+            SearchResult {
+                best_move: Some(p_move),
+                score: child_sign * result.score,
+                depth: result.depth + moves_played,
+                pv,
+                nodes: result.nodes,
+            }
+        })
+        .collect();
+    results.sort_by_key(|result| Reverse(result.score));
+
+    results
+}
+
+/// Runs iterative deepening search from the board in a single thread,
+/// until the soft time limit or the maximum depth is reached, or the search is stopped.
+/// Returns the deepest completed depth along with the best move found
+/// and its value, which are taken from the last iteration if it was cut
+/// short after searching some of the moves. The depth is at most the
+/// number of empty squares. If not even the first move of the first
+/// iteration is searched, the depth and value are 0 and the move is the
+/// one whose child the evaluation function values highest.
+///
+/// # Arguments
+///
+/// * `board` - The starting board used as the root of the search tree, which must have a legal move.
+/// * `start_depth` - The depth of the first iteration.
+/// * `time` - The TimeManager keeping track of the time budget of the search.
+/// * `table` - The transposition table shared by all threads.
+/// * `config` - The settings of the search.
+fn iterative_deepening(
+    board: &OthelloPosition,
+    start_depth: u32,
+    time: &TimeManager,
+    table: &TranspositionTable,
+    config: &SearchConfig,
+) -> ThreadResult {
+    let mut depth_limit = start_depth;
     let mut children = board.generate_children();
-    let mut value_map = HashMap::new();
-    while depth_limit <= max_depth
-        && Instant::now().duration_since(start_time) < Duration::new(time_limit, 0)
-    {
-        best_move = alphabeta_at_root(
-            board,
-            &mut children,
-            depth_limit,
-            start_time,
-            time_limit,
-            &mut value_map,
-        );
-        depth_limit += 1;
-        if board.max_player {
-            children.sort_by(|a, b| b.score.cmp(&a.score)); // Descending
-        } else {
-            children.sort_by(|a, b| a.score.cmp(&b.score)); // Ascending
+    // Should not even the first move of the first iteration be searched,
+    // the move leading to the child valued highest by the evaluation
+    // function is played.
+    let sign = if board.max_player() { 1 } else { -1 };
+    children.sort_by_cached_key(|(_, child)| Reverse(sign * config.evaluator.evaluate(child)));
+    let mut best_move = children[0].0;
+    let mut score = 0;
+    let mut completed_depth = 0;
+    let mut context = SearchContext {
+        time,
+        table,
+        evaluator: config.evaluator.as_ref(),
+        ordering: MoveOrdering::new(),
+        endgame_empties: config.endgame_empties,
+        nodes: 0,
+        canonical_table: config.canonical_table,
+    };
+    // Once the children are solved exactly, deeper iterations change nothing.
+    let empties = board.empty_squares().count_ones();
+    let solved_at_root = empties <= context.endgame_empties + 1;
+    // An iteration as deep as the number of empty squares reaches the end
+    // of the game in every line without a pass, and the deeper iterations
+    // would mostly find the boards below solved in the transposition table.
+    let last_depth = config.max_depth.min(empties);
+    while depth_limit <= last_depth && time.can_start_iteration() {
+        match alphabeta_at_root(&mut children, depth_limit, &mut context) {
+            Ok((p_move, value)) => {
+                best_move = p_move;
+                score = value;
+            }
+            Err(partial) => {
+                // The best move of an unfinished iteration is still better
+                // founded than the move of the last one, or an arbitrary move.
+                if let Some((p_move, value)) = partial {
+                    best_move = p_move;
+                    score = value;
+                }
+                break;
+            }
+        }
+        completed_depth = depth_limit;
+        if solved_at_root {
+            break;
         }
+        depth_limit += 1;
+        // The best move of this iteration is searched first in the next.
+        children.sort_by_key(|(_, child)| Reverse(child.score()));
     }
 
-    best_move
+    ThreadResult {
+        depth: completed_depth,
+        best_move,
+        score,
+        nodes: context.nodes,
+    }
 }
 
+/// Returns the best move followed by the moves expected to be played after
+/// it, found by following the best moves stored in the transposition table.
+/// The variation ends once the game is over or the table has no legal best
+/// move for a board, which is the case for boards solved by the endgame solver.
+///
+/// # Arguments
+///
+/// * `board` - The board the best move is played in.
+/// * `best_move` - The best move found by the search.
+/// * `table` - The transposition table filled by the search.
+/// * `canonical_table` - Whether the table holds boards under their canonical orientation.
+fn principal_variation(
+    board: &OthelloPosition,
+    best_move: Move,
+    table: &TranspositionTable,
+    canonical_table: bool,
+) -> Vec<Move> {
+    let mut pv = vec![best_move];
+    let mut board = Move::make_move(board, &best_move);
+    while pv.len() < MAX_GAME_LENGTH && !board.is_game_over() {
+        let moves = get_moves(&board);
+        let p_move = if moves.is_empty() {
+            Move::pass(board.to_move())
+        } else {
+            // The stored move may belong to another board sharing the slot.
+            let (key, symmetry) = table_key(&board, canonical_table);
+            let table_move = table.probe(key).and_then(|entry| entry.best_move);
+            match table_move.map(|p_move| transform_move(&p_move, inverse(symmetry))) {
+                Some(p_move) if moves.contains(&p_move) => p_move,
+                _ => break,
+            }
+        };
+        pv.push(p_move);
+        board = Move::make_move(&board, &p_move);
+    }
+
+    pv
+}
 
 /// Iterates over the children of the board representing
 /// the root of the search tree used to find the best move.
-/// From the best child, get_move_from_board_diff is called
-/// in order to generate the recommended move.
-/// 
+/// The score of each child is set to its value from the
+/// perspective of the player to go next in the board, or
+/// an upper bound of it if it was proven worse than the best.
+/// Returns the move leading to the best child along with its
+/// value. If the search is aborted by the hard time limit, returns
+/// as an error the best move among the children searched so far
+/// along with its value, or None if not even the first child was searched.
+///
 /// # Arguments
-/// 
-/// * `board` - An OthelloPosition representing the board to start the search from.
-/// * `children` - The children of the starting board. Passed to reduce computation.
+///
+/// * `children` - The children of the starting board, ordered with the most promising first.
 /// * `depth_limit` - The maximum depth to search to.
-/// * `start_time` - An Instant representing the time the program execution started.
-/// * `time_limit` - An integer representing the maximum allowed search time in seconds.
-/// * `value_map` - A HashMap containing String representations of boards and the evaluated values of the corresponding board.
-/// 
-pub fn alphabeta_at_root(
-    board: &OthelloPosition,
-    children: &mut Vec<OthelloPosition>,
+/// * `context` - The state shared by all nodes of the search.
+fn alphabeta_at_root(
+    children: &mut [(Move, OthelloPosition)],
     depth_limit: u32,
-    start_time: Instant,
-    time_limit: u64,
-    value_map: &mut HashMap<String, isize>,
-) -> Option<Move> {
-    if children.len() == 0 {
-        return None;
-    }
-    if board.max_player {
-        let mut best_child = &OthelloPosition::worst_for_max(); //this should be replaced by any child
-        let mut to_beat = VERY_LOW; // basically negative infinity as an integer
-        for child in children {
-            let child_value: isize;
-            match value_map.get(&child.string_rep()) {
-                Some(value) => child_value = *value,
-                None => {
-                    child_value = alphabeta(
-                        child,
-                        depth_limit,
-                        VERY_LOW,
-                        VERY_HIGH,
-                        start_time,
-                        time_limit,
-                    );
-                    value_map.insert(child.string_rep(), child_value);
-                }
-            }
-            child.score = child_value;
-            if child_value >= to_beat {
-                to_beat = child_value;
-                best_child = child;
-            }
-        }
-        get_move_from_board_diff(board, &best_child)
-    } else {
-        let mut best_child = &OthelloPosition::worst_for_min();
-        let mut to_beat = VERY_HIGH;
-        for child in children {
-            let child_value: isize;
-            match value_map.get(&child.string_rep()) {
-                Some(value) => child_value = *value,
-                None => {
-                    child_value = alphabeta(
-                        child,
-                        depth_limit,
-                        VERY_LOW,
-                        VERY_HIGH,
-                        start_time,
-                        time_limit,
-                    );
-                    value_map.insert(child.string_rep(), child_value);
-                }
-            }
-            child.score = child_value;
-            if child_value <= to_beat {
-                to_beat = child_value;
-                best_child = child;
+    context: &mut SearchContext,
+) -> Result<(Move, isize), Option<(Move, isize)>> {
+    let mut alpha = VERY_LOW;
+    let mut best = None;
+    for (i, (p_move, child)) in children.iter_mut().enumerate() {
+        let child_value = if i == 0 {
+            -alphabeta(child, depth_limit, -VERY_HIGH, -alpha, 1, context).ok_or(best)?
+        } else {
+            let bound =
+                -alphabeta(child, depth_limit, -alpha - 1, -alpha, 1, context).ok_or(best)?;
+            if bound > alpha {
+                // The move is proven better than the best so far, so it is
+                // kept should the search be aborted before its exact value is known.
+                let improved = Some((*p_move, bound));
+                -alphabeta(child, depth_limit, -VERY_HIGH, -alpha, 1, context).ok_or(improved)?
+            } else {
+                bound
             }
+        };
+        child.set_score(child_value);
+        if i == 0 || child_value > alpha {
+            alpha = child_value;
+            best = Some((*p_move, child_value));
         }
-        get_move_from_board_diff(board, &best_child)
     }
+
+    best.ok_or(None)
 }
 
-/// The actual minimax algorithm with alpha-beta pruning.
-/// Evaluates the given board if the depth limit is reached, no moves are possible,
-/// or if the time limit is reached.
-/// 
+/// The actual principal variation search.
+/// Returns the value of the board from the perspective of the player to go next.
+/// Finished games, and boards with few enough empty squares to be solved,
+/// are scored by their exact piece difference. Other boards are evaluated
+/// heuristically once the depth limit is reached. Returns None
+/// if the hard time limit is reached, in which case nothing is stored in the
+/// transposition table and the value of the iteration must be discarded.
+/// A player without legal moves passes, which counts as a move of its own.
+/// Every searched board is stored in the transposition table, and stored
+/// results are used either directly or to narrow the search window.
+///
 /// # Arguments
-/// 
+///
 /// * `board` - An OthelloPosition instance representing the board to be evaluated.
 /// * `depth` - An integer representing the depth remaining until the search depth, decreases with each call.
 /// * `alpha` - An integer representing the alpha parameter used for pruning.
 /// * `beta` - An integer representing the beta parameter used for pruning.
-/// * `start_time` - An Instant representing the time of program execution start.
-/// * `time_limit` - An integer representing the maximum allowed search time in seconds.
-pub fn alphabeta(
+/// * `ply` - The distance from the root of the search to the board.
+/// * `context` - The state shared by all nodes of the search.
+fn alphabeta(
     board: &OthelloPosition,
     depth: u32,
     mut alpha: isize,
     mut beta: isize,
-    start_time: Instant,
-    time_limit: u64,
-) -> isize {
-    
-    if depth == 0
-        || board.is_game_over()
-        || Instant::now().duration_since(start_time) > Duration::new(time_limit, 0)
-    {
-        let value = Evaluator::default().evaluate(board);
-        return value;
-    }
-
-    if board.max_player {
-        let mut value = VERY_LOW;
-        for mut child in board.generate_children() {
-            let child_value = alphabeta(&child, depth - 1, alpha, beta, start_time, time_limit);
-            child.score = child_value;
-            if child_value >= value {
-                value = child_value;
+    ply: usize,
+    context: &mut SearchContext,
+) -> Option<isize> {
+    if context.time.is_out_of_time() {
+        return None;
+    }
+    context.nodes += 1;
+    let sign = if board.max_player() { 1 } else { -1 };
+    if board.is_game_over() {
+        return Some(sign * Evaluator::final_score(board));
+    }
+    let in_endgame = board.empty_squares().count_ones() <= context.endgame_empties;
+    if depth == 0 && !in_endgame {
+        let value = context.evaluator.evaluate(board);
+        return Some(sign * value);
+    }
+
+    let (key, symmetry) = table_key(board, context.canonical_table);
+    let mut table_move = None;
+    if let Some(entry) = context.table.probe(key) {
+        table_move = entry
+            .best_move
+            .map(|p_move| transform_move(&p_move, inverse(symmetry)));
+        if entry.depth >= depth {
+            match entry.bound {
+                Bound::Exact => return Some(entry.value),
+                Bound::Lower => alpha = alpha.max(entry.value),
+                Bound::Upper => beta = beta.min(entry.value),
             }
-            if value >= beta {
-                break;
+            if alpha >= beta {
+                return Some(entry.value);
             }
-            if value > alpha {
-                alpha = value;
+        }
+    }
+    let original_alpha = alpha;
+
+    if in_endgame {
+        // Widen the window to whole pieces so that the bounds stay valid.
+        let piece_alpha = alpha.div_euclid(FINAL_DISC_WEIGHT);
+        let piece_beta = -(-beta).div_euclid(FINAL_DISC_WEIGHT);
+        let mut solver = EndgameSolver::new(context.time);
+        let solved = solver.solve(board, piece_alpha, piece_beta);
+        context.nodes += solver.nodes;
+        let value = FINAL_DISC_WEIGHT * solved?;
+        store(context.table, key, SOLVED_DEPTH, original_alpha, beta, value, None);
+        return Some(value);
+    }
+
+    let mut moves = get_moves(board);
+    if moves.is_empty() {
+        // The game is not over, so the opponent can move after a pass.
+        return Some(-alphabeta(&board.pass(), depth - 1, -beta, -alpha, ply + 1, context)?);
+    }
+    context.ordering.order(&mut moves, table_move, ply, board.max_player());
+
+    let mut value = VERY_LOW;
+    let mut best_move = None;
+    for (i, p_move) in moves.into_iter().enumerate() {
+        let child = Move::make_move(board, &p_move);
+        let child_value = if i == 0 {
+            -alphabeta(&child, depth - 1, -beta, -alpha, ply + 1, context)?
+        } else {
+            // Try to prove the move is no better than the best so far,
+            // and only search it with the full window if that fails.
+            let bound = -alphabeta(&child, depth - 1, -alpha - 1, -alpha, ply + 1, context)?;
+            if bound > alpha && bound < beta {
+                -alphabeta(&child, depth - 1, -beta, -alpha, ply + 1, context)?
+            } else {
+                bound
             }
+        };
+        if child_value > value {
+            value = child_value;
+            best_move = Some(p_move);
+        }
+        if value > alpha {
+            alpha = value;
+        }
+        if alpha >= beta {
+            context.ordering.record_cutoff(p_move, ply, depth, board.max_player());
+            break;
         }
-        value
+    }
+
+    let best_move = best_move.map(|p_move| transform_move(&p_move, symmetry));
+    store(context.table, key, depth, original_alpha, beta, value, best_move);
+
+    Some(value)
+}
+
+/// Returns the key of the board in the transposition table, along with the
+/// symmetry moving the board to the orientation its moves are stored in.
+fn table_key(board: &OthelloPosition, canonical_table: bool) -> (u64, usize) {
+    if canonical_table {
+        let (canonical_board, symmetry) = canonical(board);
+        (canonical_board.hash(), symmetry)
     } else {
-        let mut value = VERY_HIGH;
-        for mut child in board.generate_children() {
-            let child_value = alphabeta(&child, depth - 1, alpha, beta, start_time, time_limit);
-            child.score = child_value;
-            if child_value <= value {
-                value = child_value;
-            }
-            if value <= alpha {
-                break;
-            }
-            if value < beta {
-                beta = value;
-            }
+        (board.hash(), 0)
+    }
+}
+
+/// Stores the value of a board, with the given key, searched with the given
+/// window in the transposition table, along with the kind of bound the value is.
+fn store(
+    table: &TranspositionTable,
+    key: u64,
+    depth: u32,
+    alpha: isize,
+    beta: isize,
+    value: isize,
+    best_move: Option<Move>,
+) {
+    let bound = if value <= alpha {
+        Bound::Upper
+    } else if value >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    table.store(Entry {
+        key,
+        depth,
+        bound,
+        value,
+        best_move,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_time_manager_can_be_used_for_several_searches() {
+        let board = OthelloPosition::start();
+        let config = SearchConfig {
+            threads: 2,
+            max_depth: 3,
+            ..SearchConfig::default()
+        };
+        let time = TimeManager::new(Instant::now(), 60_000);
+        for _ in 0..2 {
+            let result = search(&board, &time, &config);
+            assert_eq!(result.depth, 3);
         }
-        value
     }
 }
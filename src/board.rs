@@ -1,153 +1,394 @@
-/// This file contains the representation of a board used in Othello/Reversi.
+//! This file contains the representation of a board used in Othello/Reversi.
+//! The pieces are stored as bitboards, where bit `row * 8 + col` is set
+//! if the square in the given (zero-based) row and column holds a piece.
 
-
-use crate::move_generator::{get_moves, Move};
+use crate::move_generator::{flips, get_moves, move_mask, Move};
+use crate::zobrist;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 pub const PLAYER_WHITE: char = 'O';
 pub const PLAYER_BLACK: char = 'X';
 pub const EMPTY_CELL: char = 'E';
 pub const WHITE_STRING_REP: char = 'W';
 pub const BLACK_STRING_REP: char = 'B';
 pub const BOARD_SIZE: usize = 8;
+/// The length of the string representation of a board: one char
+/// for the player to go next followed by one char per square.
+pub const STRING_REP_LENGTH: usize = BOARD_SIZE * BOARD_SIZE + 1;
+/// The number of pieces on the board when the game starts.
+const STARTING_PIECES: u32 = 4;
+
+/// The reasons a string can fail to represent an OthelloPosition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsePositionError {
+    /// The string does not contain exactly 65 chars. Holds the actual length.
+    BadLength(usize),
+    /// The first char is neither 'W' nor 'B'. Holds the char found.
+    UnknownPlayer(char),
+    /// A square is neither 'O', 'X' nor 'E'. Holds the zero-based index
+    /// of the square along with the char found.
+    InvalidCell(usize, char),
+    /// The board holds fewer pieces than any reachable position.
+    /// Holds the number of white and black pieces.
+    ImpossibleDiscCount(u32, u32),
+}
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsePositionError::BadLength(length) => write!(
+                f,
+                "expected {} characters but found {}",
+                STRING_REP_LENGTH, length
+            ),
+            ParsePositionError::UnknownPlayer(c) => write!(
+                f,
+                "expected '{}' or '{}' as the player to move but found '{}'",
+                WHITE_STRING_REP, BLACK_STRING_REP, c
+            ),
+            ParsePositionError::InvalidCell(index, c) => write!(
+                f,
+                "expected '{}', '{}' or '{}' in row {}, column {} but found '{}'",
+                PLAYER_WHITE,
+                PLAYER_BLACK,
+                EMPTY_CELL,
+                index / BOARD_SIZE + 1,
+                index % BOARD_SIZE + 1,
+                c
+            ),
+            ParsePositionError::ImpossibleDiscCount(white, black) => write!(
+                f,
+                "{} white and {} black pieces can not be reached from the starting position",
+                white, black
+            ),
+        }
+    }
+}
+
+impl Error for ParsePositionError {}
 
+/// Returns the bit representing the square in the given zero-based row and column.
+pub fn square_bit(row: usize, col: usize) -> u64 {
+    1 << (row * BOARD_SIZE + col)
+}
 
 /// The representation of a given Othello position,
 /// includes the pieces on the board, the player who is to
 /// play next, and the evaluated score of a given board.
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct OthelloPosition {
-    /// The pieces on the board. The actual playable area is 8x8, not 10x10.
-    pub board: [[char; 10]; 10],
+    /// The pieces belonging to the player who is to play next.
+    player: u64,
+    /// The pieces belonging to the opponent of the player who is to play next.
+    opponent: u64,
     // Represents which player should go next.
-    pub max_player: bool,
+    max_player: bool,
     // The evaluated score of this board.
-    pub score: isize,
+    score: isize,
+    /// The Zobrist hash of the pieces and the player to go next.
+    hash: u64,
 }
 
 impl OthelloPosition {
-    /// Returns a new, empty board.
-    /// Assumes the max player plays first.
-    pub fn empty() -> OthelloPosition {
-        let board = [[EMPTY_CELL; 10]; 10];
-
+    /// Returns an OthelloPosition with the given white and black pieces,
+    /// where the max player goes next if `max_player` is true.
+    pub fn from_colours(white: u64, black: u64, max_player: bool) -> OthelloPosition {
+        let (player, opponent) = if max_player {
+            (white, black)
+        } else {
+            (black, white)
+        };
         OthelloPosition {
-            board,
-            max_player: true,
+            player,
+            opponent,
+            max_player,
             score: 0,
+            hash: zobrist::hash(white, black, max_player),
         }
     }
 
-    /// Returns a board representing the worst possible
-    /// case for the max player.
-    pub fn worst_for_max() -> OthelloPosition {
-        let board = [[PLAYER_BLACK; 10]; 10];
-        OthelloPosition {
-            board,
-            max_player: true,
-            score: 0,
+    /// Returns the position the game starts from, with two pieces of each
+    /// colour on the diagonals of the centre and black to go next.
+    pub fn start() -> OthelloPosition {
+        OthelloPosition::from_colours(
+            square_bit(3, 3) | square_bit(4, 4),
+            square_bit(3, 4) | square_bit(4, 3),
+            false,
+        )
+    }
+
+    /// Returns whether the white (max) player is to play next. The side to
+    /// move only changes by playing a move or passing.
+    pub fn max_player(&self) -> bool {
+        self.max_player
+    }
+
+    /// Returns the pieces belonging to the player who is to play next.
+    pub fn player(&self) -> u64 {
+        self.player
+    }
+
+    /// Returns the pieces belonging to the opponent of the player who is to play next.
+    pub fn opponent(&self) -> u64 {
+        self.opponent
+    }
+
+    /// Returns the Zobrist hash of the pieces and the player to go next.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the evaluated score of the board, which is 0 unless set by the search.
+    pub fn score(&self) -> isize {
+        self.score
+    }
+
+    /// Sets the evaluated score of the board.
+    pub fn set_score(&mut self, score: isize) {
+        self.score = score;
+    }
+
+    /// Returns the pieces belonging to the white (max) player.
+    pub fn white(&self) -> u64 {
+        if self.max_player {
+            self.player
+        } else {
+            self.opponent
         }
     }
 
-    /// Returns a board representing the worst possible
-    /// case for the min player.
-    pub fn worst_for_min() -> OthelloPosition {
-        let board = [[PLAYER_WHITE; 10]; 10];
-        OthelloPosition {
-            board,
-            max_player: false,
-            score: 0,
+    /// Returns the pieces belonging to the black (min) player.
+    pub fn black(&self) -> u64 {
+        if self.max_player {
+            self.opponent
+        } else {
+            self.player
+        }
+    }
+
+    /// Returns the char representation of the colour of the player to go next.
+    pub fn to_move(&self) -> char {
+        if self.max_player {
+            PLAYER_WHITE
+        } else {
+            PLAYER_BLACK
+        }
+    }
+
+    /// Returns the squares not holding a piece of either player.
+    pub fn empty_squares(&self) -> u64 {
+        !(self.player | self.opponent)
+    }
+
+    /// Returns the char representation of the piece in the given row and
+    /// column, where the playable area is indexed from 1 to 8. Rows and
+    /// columns 0 and 9 lie outside the board and are always empty.
+    pub fn cell(&self, row: usize, col: usize) -> char {
+        if row == 0 || col == 0 || row > BOARD_SIZE || col > BOARD_SIZE {
+            return EMPTY_CELL;
+        }
+        let bit = square_bit(row - 1, col - 1);
+        if self.white() & bit != 0 {
+            PLAYER_WHITE
+        } else if self.black() & bit != 0 {
+            PLAYER_BLACK
+        } else {
+            EMPTY_CELL
         }
     }
 
     /// Returns a boolean representing whether or not it is possible
     /// for either player to make more moves with the given state of
-    /// the current board.
+    /// the current board. The game is only over once both players
+    /// would have to pass.
     pub fn is_game_over(&self) -> bool {
-        for row in 1..=8 {
-            for col in 1..=8 {
-                if self.board[row][col] == 'E' {
-                    return false;
-                }
-            }
-        }
+        move_mask(self.player, self.opponent) == 0 && move_mask(self.opponent, self.player) == 0
+    }
 
-        true
+    /// Returns the difference between the number of white and black
+    /// pieces on the board. Positive values favour the max player.
+    pub fn disc_difference(&self) -> isize {
+        self.white().count_ones() as isize - self.black().count_ones() as isize
     }
 
-    /// Returns an instance of OthelloPosition representing the board
-    /// represented in the given string.
-    /// 
+    /// Places a piece for the player to go next in the given zero-based
+    /// row and column and flips the pieces it captures.
+    /// Does not check that the move is legal.
+    /// Does not mutate in-place, returns a brand new OthelloPosition.
+    ///
     /// # Arguments
-    /// 
-    /// * `string_rep` - A string representation of an Othello board, along with the player to go next.
-    pub fn new(string_rep: &str) -> OthelloPosition {
-        if string_rep.len() != 65 {
-            OthelloPosition::empty()
+    ///
+    /// * `row` - Which row to add the piece to.
+    /// * `col` - Which column to add the piece to.
+    pub fn play(&self, row: usize, col: usize) -> OthelloPosition {
+        let square = row * BOARD_SIZE + col;
+        let flipped = flips(self.player, self.opponent, square);
+        let placed_key = if self.max_player {
+            zobrist::WHITE_KEYS[square]
         } else {
-            let mut board = [[EMPTY_CELL; 10]; 10];
-            let mut max_player = false;
-            if string_rep.chars().collect::<Vec<char>>()[0] == WHITE_STRING_REP {
-                max_player = true;
-            }
-            for i in 1..=64 {
-                let c = string_rep.chars().collect::<Vec<char>>()[i];
-                let col = ((i - 1) % BOARD_SIZE) + 1;
-                let row = (i - 1) / 8 + 1;
-                board[row][col] = c;
+            zobrist::BLACK_KEYS[square]
+        };
+        let hash = self.hash
+            ^ placed_key
+            ^ zobrist::pieces_hash(flipped, &zobrist::FLIP_KEYS)
+            ^ zobrist::MAX_PLAYER_KEY;
+        OthelloPosition {
+            player: self.opponent ^ flipped,
+            opponent: self.player | flipped | square_bit(row, col),
+            max_player: !self.max_player,
+            score: 0,
+            hash,
+        }
+    }
+
+    /// Returns the board resulting from the player to go next passing,
+    /// i.e. the same pieces with the other player to go next.
+    pub fn pass(&self) -> OthelloPosition {
+        OthelloPosition {
+            player: self.opponent,
+            opponent: self.player,
+            max_player: !self.max_player,
+            score: 0,
+            hash: self.hash ^ zobrist::MAX_PLAYER_KEY,
+        }
+    }
+
+    /// Generates the boards reachable in one move from the board, each
+    /// along with the move leading to it. If the player to go next has no
+    /// legal moves but the opponent does, the only child is the board
+    /// resulting from passing. If neither player can move the game is over
+    /// and no children are returned.
+    pub fn generate_children(&self) -> Vec<(Move, OthelloPosition)> {
+        let possible_moves = get_moves(self);
+        if possible_moves.is_empty() {
+            if move_mask(self.opponent, self.player) != 0 {
+                return vec![(Move::pass(self.to_move()), self.pass())];
             }
-            OthelloPosition {
-                board,
-                max_player,
-                score: 0,
+            return Vec::new();
+        }
+
+        possible_moves
+            .into_iter()
+            .map(|p_move| (p_move, Move::make_move(self, &p_move)))
+            .collect()
+    }
+}
+
+impl FromStr for OthelloPosition {
+    type Err = ParsePositionError;
+
+    /// Parses a string representation of an Othello board, along with the
+    /// player to go next. The first char is 'W' or 'B' for the player to go
+    /// next, followed by 64 chars listing the squares row by row, where 'O'
+    /// is a white piece, 'X' is a black piece and 'E' is an empty square.
+    fn from_str(string_rep: &str) -> Result<OthelloPosition, ParsePositionError> {
+        let length = string_rep.chars().count();
+        if length != STRING_REP_LENGTH {
+            return Err(ParsePositionError::BadLength(length));
+        }
+        let mut chars = string_rep.chars();
+        let max_player = match chars.next() {
+            Some(WHITE_STRING_REP) => true,
+            Some(BLACK_STRING_REP) => false,
+            Some(c) => return Err(ParsePositionError::UnknownPlayer(c)),
+            None => return Err(ParsePositionError::BadLength(0)),
+        };
+        let mut white: u64 = 0;
+        let mut black: u64 = 0;
+        for (i, c) in chars.enumerate() {
+            match c {
+                PLAYER_WHITE => white |= 1 << i,
+                PLAYER_BLACK => black |= 1 << i,
+                EMPTY_CELL => (),
+                _ => return Err(ParsePositionError::InvalidCell(i, c)),
             }
         }
+        if white.count_ones() + black.count_ones() < STARTING_PIECES {
+            return Err(ParsePositionError::ImpossibleDiscCount(
+                white.count_ones(),
+                black.count_ones(),
+            ));
+        }
+
+        Ok(OthelloPosition::from_colours(white, black, max_player))
     }
+}
 
-    /// Returns the string representation of an OthelloPosition.
-    /// Matches the format used to create an OthelloPosition in new(string_rep: &str).
-    pub fn string_rep(&self) -> String {
-        let mut player_char = BLACK_STRING_REP;
+impl fmt::Display for OthelloPosition {
+    /// Writes the string representation of the board, in the format
+    /// accepted by from_str.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.max_player {
-            player_char = WHITE_STRING_REP;
+            write!(f, "{}", WHITE_STRING_REP)?;
+        } else {
+            write!(f, "{}", BLACK_STRING_REP)?;
         }
-        let mut to_return = String::from(player_char);
         for row in 1..=BOARD_SIZE {
             for col in 1..=BOARD_SIZE {
-                to_return.push_str(&format!("{}", self.board[row][col]));
+                write!(f, "{}", self.cell(row, col))?;
             }
         }
 
-        to_return
+        Ok(())
     }
+}
 
-    /// Adds a given piece in the given row and column of the board.
-    /// Does not check if this conflicts with the current state of the board.
-    /// Does not mutate in-place, returns a brand new OthelloPosition.
-    /// 
-    /// # Arguments
-    /// 
-    /// * `row` - Which row to add the piece to.
-    /// * `col` - Which column to add the piece to.
-    /// * `player` - A char representation of the colour of the piece to be added.
-    pub fn add_piece(&self, row: usize, col: usize, player: char) -> OthelloPosition {
-        let mut new_position = OthelloPosition {
-            board: self.board.clone(),
-            max_player: !self.max_player,
-            score: 0,
-        };
-        new_position.board[row][col] = player;
-        new_position
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The starting position, with black to move.
+    const START: &str = "BEEEEEEEEEEEEEEEEEEEEEEEEEEEOXEEEEEEXOEEEEEEEEEEEEEEEEEEEEEEEEEEE";
+
+    #[test]
+    fn the_string_representation_reads_back_the_same() {
+        let board: OthelloPosition = START.parse().unwrap();
+        assert_eq!(board, OthelloPosition::start());
+        assert_eq!(board.to_string(), START);
+        let white_to_move = START.replacen('B', "W", 1);
+        let board: OthelloPosition = white_to_move.parse().unwrap();
+        assert!(board.max_player());
+        assert_eq!(board.to_string(), white_to_move);
+        // A board reached after two moves.
+        let (_, played) = &OthelloPosition::start().generate_children()[0];
+        let (_, played) = &played.generate_children()[0];
+        assert_eq!(played.to_string().parse::<OthelloPosition>().as_ref(), Ok(played));
     }
 
+    #[test]
+    fn strings_of_the_wrong_length_are_rejected() {
+        assert_eq!("".parse::<OthelloPosition>(), Err(ParsePositionError::BadLength(0)));
+        assert_eq!(
+            START[..64].parse::<OthelloPosition>(),
+            Err(ParsePositionError::BadLength(64))
+        );
+        let longer = format!("{}E", START);
+        assert_eq!(longer.parse::<OthelloPosition>(), Err(ParsePositionError::BadLength(66)));
+    }
 
-    /// Generates the boards reachable in one move from the board.
-    pub fn generate_children(&self) -> Vec<OthelloPosition> {
-        let possible_moves = get_moves(&self);
-        let mut child_boards = Vec::new();
+    #[test]
+    fn an_unknown_player_to_move_is_rejected() {
+        let board = START.replacen('B', "X", 1);
+        assert_eq!(board.parse::<OthelloPosition>(), Err(ParsePositionError::UnknownPlayer('X')));
+    }
 
-        for p_move in possible_moves {
-            child_boards.push(Move::make_move(&self, &p_move));
-        }
+    #[test]
+    fn an_invalid_square_is_reported_by_row_and_column() {
+        // The eleventh square is in the second row and the third column.
+        let mut board = String::from(START);
+        board.replace_range(11..12, "?");
+        let error = board.parse::<OthelloPosition>().unwrap_err();
+        assert_eq!(error, ParsePositionError::InvalidCell(10, '?'));
+        assert!(error.to_string().contains("row 2, column 3"));
+    }
 
-        child_boards
+    #[test]
+    fn too_few_pieces_are_rejected() {
+        let board = START.replacen('O', "E", 1);
+        assert_eq!(
+            board.parse::<OthelloPosition>(),
+            Err(ParsePositionError::ImpossibleDiscCount(1, 2))
+        );
     }
 }
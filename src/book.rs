@@ -0,0 +1,416 @@
+//! This file contains the opening book, which stores the values of the
+//! moves of boards analysed ahead of time so that the first moves of a game
+//! can be played without a search. The book is keyed by the hash of the
+//! canonical orientation of each board (see the symmetry module), so a board
+//! is found in the book however it is rotated or reflected, and its moves
+//! are stored in the canonical orientation and turned back on lookup.
+//!
+//! Each book move has a value, in hundredths of a piece from the perspective
+//! of the player to move, and a weight. Moves are picked at random in
+//! proportion to their weights, which gives the engine some variety in the
+//! opening, and moves with a weight of zero are never picked.
+//!
+//! On disk, the book is the magic bytes `OBK1`, the number of moves as a
+//! 32-bit integer, and then the moves ordered by key and square, each as the
+//! 64-bit key, the 8-bit square, the 16-bit value and the 16-bit weight.
+//! All integers are little endian.
+
+use crate::board::{OthelloPosition, BOARD_SIZE};
+use crate::engine::Engine;
+use crate::move_generator::{get_moves, Move};
+use crate::symmetry::{canonical, inverse, transform_move};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The bytes every book file starts with.
+const MAGIC: &[u8; 4] = b"OBK1";
+/// The number of bytes of each move on disk.
+const ENTRY_BYTES: usize = 13;
+
+/// A move of the book, as stored in the canonical orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    square: u8,
+    value: i16,
+    weight: u16,
+}
+
+/// A move found in the book for a board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    pub p_move: Move,
+    /// The value of the move in hundredths of a piece, from the
+    /// perspective of the player making it.
+    pub value: i16,
+    /// How often the move is picked relative to the other moves of the board.
+    pub weight: u16,
+}
+
+/// A collection of analysed boards and the values of their moves.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: HashMap<u64, Vec<Entry>>,
+}
+
+impl Book {
+    /// Returns a new, empty book.
+    pub fn new() -> Book {
+        Book::default()
+    }
+
+    /// Returns the number of boards in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the book holds no boards.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns whether the board, in any orientation, is in the book.
+    pub fn contains(&self, board: &OthelloPosition) -> bool {
+        self.entries.contains_key(&canonical(board).0.hash())
+    }
+
+    /// Stores a move of the board in the book, replacing the move if it
+    /// was already stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board the move is made in.
+    /// * `p_move` - The move, which must not be a pass.
+    /// * `value` - The value of the move in hundredths of a piece.
+    /// * `weight` - How often the move is picked relative to the other moves of the board.
+    pub fn add(&mut self, board: &OthelloPosition, p_move: &Move, value: i16, weight: u16) {
+        let (canonical_board, symmetry) = canonical(board);
+        let canonical_move = transform_move(p_move, symmetry);
+        let square = (canonical_move.row * BOARD_SIZE + canonical_move.col) as u8;
+        let entries = self.entries.entry(canonical_board.hash()).or_default();
+        let entry = Entry {
+            square,
+            value,
+            weight,
+        };
+        match entries.iter_mut().find(|entry| entry.square == square) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+    }
+
+    /// Returns the moves stored for the board, in the orientation of the board.
+    pub fn moves(&self, board: &OthelloPosition) -> Vec<BookMove> {
+        let (canonical_board, symmetry) = canonical(board);
+        let entries = match self.entries.get(&canonical_board.hash()) {
+            Some(entries) => entries,
+            None => return Vec::new(),
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                let square = entry.square as usize;
+                let canonical_move =
+                    Move::new(board.to_move(), square / BOARD_SIZE, square % BOARD_SIZE);
+                BookMove {
+                    p_move: transform_move(&canonical_move, inverse(symmetry)),
+                    value: entry.value,
+                    weight: entry.weight,
+                }
+            })
+            .collect()
+    }
+
+    /// Picks one of the moves stored for the board at random, in proportion
+    /// to their weights. Returns None if the board has no move with a weight
+    /// above zero in the book.
+    pub fn choose<R: Rng>(&self, board: &OthelloPosition, rng: &mut R) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for book_move in moves {
+            if pick < book_move.weight as u32 {
+                return Some(book_move.p_move);
+            }
+            pick -= book_move.weight as u32;
+        }
+
+        None
+    }
+
+    /// Adds the moves of a finished game to the book, valued by the final
+    /// piece difference from the perspective of the player making each
+    /// move. Moves of the players who did not lose are given a weight of
+    /// one, and the others a weight of zero. Moves already in the book and
+    /// passes are left out. Returns the number of moves added.
+    ///
+    /// # Arguments
+    ///
+    /// * `moves` - The boards of the game, each along with the move made in it.
+    /// * `final_board` - The board the game ended on.
+    pub fn learn(
+        &mut self,
+        moves: &[(OthelloPosition, Move)],
+        final_board: &OthelloPosition,
+    ) -> usize {
+        let mut added = 0;
+        for (board, p_move) in moves {
+            if p_move.is_pass() || self.moves(board).iter().any(|known| known.p_move == *p_move) {
+                continue;
+            }
+            let sign = if board.max_player() { 1 } else { -1 };
+            let difference = sign * final_board.disc_difference();
+            let weight = if difference >= 0 { 1 } else { 0 };
+            self.add(board, p_move, (difference * 100) as i16, weight);
+            added += 1;
+        }
+
+        added
+    }
+
+    /// Expands the book by analysing the boards reachable from the start
+    /// within the given number of moves, following only moves worth playing.
+    /// Every move of an analysed board is searched for its value, and the
+    /// moves within the margin of the best are given weights falling with
+    /// their distance to it, while the others get a weight of zero. Boards
+    /// already in the book are not analysed again. Returns the number of
+    /// boards analysed.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The engine searching the moves, whose game is replaced.
+    /// * `start` - The board to expand the book from.
+    /// * `plies` - The number of moves from the start to analyse boards for.
+    /// * `time_ms` - The time in milliseconds spent analysing each board.
+    /// * `margin` - The greatest difference to the best move, in hundredths
+    ///   of a piece, of the moves which are played.
+    pub fn build(
+        &mut self,
+        engine: &mut Engine,
+        start: &OthelloPosition,
+        plies: u32,
+        time_ms: u64,
+        margin: i16,
+    ) -> usize {
+        let mut analysed = 0;
+        let mut frontier = vec![start.clone()];
+        let mut seen = HashSet::new();
+        seen.insert(canonical(start).0.hash());
+        for _ in 0..plies {
+            let mut next = Vec::new();
+            for board in frontier {
+                if board.is_game_over() {
+                    continue;
+                }
+                if get_moves(&board).is_empty() {
+                    next.push(board.pass());
+                    continue;
+                }
+                if !self.contains(&board) {
+                    self.analyse(engine, &board, time_ms, margin);
+                    analysed += 1;
+                }
+                for book_move in self.moves(&board) {
+                    let child = Move::make_move(&board, &book_move.p_move);
+                    if book_move.weight > 0 && seen.insert(canonical(&child).0.hash()) {
+                        next.push(child);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        analysed
+    }
+
+    /// Searches every move of the board and stores it with its value and weight.
+    fn analyse(&mut self, engine: &mut Engine, board: &OthelloPosition, time_ms: u64, margin: i16) {
+        engine.set_board(board.clone());
+        let results = engine.search_moves(time_ms);
+        let values: Vec<i16> = results
+            .iter()
+            .map(|result| {
+                (result.score_in_discs() * 100.0)
+                    .round()
+                    .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+            })
+            .collect();
+        let best = values.iter().copied().max().unwrap_or(0);
+        for (result, value) in results.iter().zip(values) {
+            let distance = best as i32 - value as i32;
+            let weight = (margin as i32 - distance + 1).clamp(0, u16::MAX as i32) as u16;
+            if let Some(p_move) = result.best_move {
+                self.add(board, &p_move, value, weight);
+            }
+        }
+    }
+
+    /// Reads a book in the format described at the top of the file.
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Book> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an opening book",
+            ));
+        }
+        let mut count = [0; 4];
+        input.read_exact(&mut count)?;
+
+        let mut book = Book::new();
+        let mut bytes = [0; ENTRY_BYTES];
+        for _ in 0..u32::from_le_bytes(count) {
+            input.read_exact(&mut bytes)?;
+            let mut key = [0; 8];
+            key.copy_from_slice(&bytes[0..8]);
+            book.entries
+                .entry(u64::from_le_bytes(key))
+                .or_default()
+                .push(Entry {
+                    square: bytes[8],
+                    value: i16::from_le_bytes([bytes[9], bytes[10]]),
+                    weight: u16::from_le_bytes([bytes[11], bytes[12]]),
+                });
+        }
+
+        Ok(book)
+    }
+
+    /// Writes the book in the format described at the top of the file.
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort_unstable();
+        let count: usize = self.entries.values().map(Vec::len).sum();
+        output.write_all(MAGIC)?;
+        output.write_all(&(count as u32).to_le_bytes())?;
+        for key in keys {
+            let mut entries = self.entries[key].clone();
+            entries.sort_unstable_by_key(|entry| entry.square);
+            for entry in entries {
+                output.write_all(&key.to_le_bytes())?;
+                output.write_all(&[entry.square])?;
+                output.write_all(&entry.value.to_le_bytes())?;
+                output.write_all(&entry.weight.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the book stored in the file at the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Book::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Stores the book in the file at the given path, replacing its contents.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write(&mut output)?;
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetry::{transform_move, transform_position, SYMMETRIES};
+
+    /// Returns a book holding two moves of the board reached after `f5`.
+    fn sample_book() -> (Book, OthelloPosition) {
+        let start = OthelloPosition::start();
+        let board = Move::make_move(&start, &Move::parse("f5", 'B').unwrap());
+        let mut book = Book::new();
+        book.add(&board, &Move::parse("d6", 'O').unwrap(), -90, 100);
+        book.add(&board, &Move::parse("f6", 'O').unwrap(), -130, 0);
+        (book, board)
+    }
+
+    #[test]
+    fn a_written_book_reads_back_the_same() {
+        let (book, board) = sample_book();
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), MAGIC.len() + 4 + 2 * ENTRY_BYTES);
+
+        let read = Book::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read.moves(&board), book.moves(&board));
+    }
+
+    #[test]
+    fn reading_rejects_other_files() {
+        assert!(Book::read(&mut b"OBK2\0\0\0\0".as_slice()).is_err());
+        // A book cut short in the middle of a move.
+        let (book, _) = sample_book();
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        bytes.pop();
+        assert!(Book::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn moves_are_found_in_every_orientation() {
+        let (book, board) = sample_book();
+        let key = |book_move: &BookMove| (book_move.p_move.row, book_move.p_move.col);
+        for symmetry in 0..SYMMETRIES {
+            let moved = transform_position(&board, symmetry);
+            let mut expected: Vec<BookMove> = book
+                .moves(&board)
+                .into_iter()
+                .map(|book_move| BookMove {
+                    p_move: transform_move(&book_move.p_move, symmetry),
+                    ..book_move
+                })
+                .collect();
+            let mut found = book.moves(&moved);
+            expected.sort_by_key(key);
+            found.sort_by_key(key);
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn learning_a_game_keeps_the_moves_already_known() {
+        let (mut book, board) = sample_book();
+        let mut moves = vec![(board.clone(), Move::parse("f6", 'O').unwrap())];
+        let mut current = Move::make_move(&board, &moves[0].1);
+        moves.push((current.clone(), Move::parse("e6", 'X').unwrap()));
+        current = Move::make_move(&current, &moves[1].1);
+        // The known move of white keeps its value, while the new move of
+        // black is valued by the lead of black at the end.
+        assert_eq!(current.disc_difference(), -3);
+        assert_eq!(book.learn(&moves, &current), 1);
+        let known = book.moves(&board);
+        assert!(known.iter().any(|book_move| book_move.value == -130));
+        assert_eq!(
+            book.moves(&moves[1].0),
+            vec![BookMove {
+                p_move: moves[1].1,
+                value: 300,
+                weight: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn choose_only_picks_moves_with_a_weight() {
+        let (book, board) = sample_book();
+        let mut rng = rand::thread_rng();
+        let d6 = Move::parse("d6", 'O').unwrap();
+        for _ in 0..20 {
+            assert_eq!(book.choose(&board, &mut rng), Some(d6));
+        }
+        // The board reached after c4 is a reflection of it, where the
+        // perpendicular reply d6 becomes e3.
+        let c4 = Move::make_move(&OthelloPosition::start(), &Move::parse("c4", 'B').unwrap());
+        assert_eq!(book.choose(&c4, &mut rng), Some(Move::parse("e3", 'O').unwrap()));
+        assert_eq!(book.choose(&OthelloPosition::start(), &mut rng), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! This file contains the endgame solver. Once few enough squares are
+//! empty, the game tree can be searched all the way to the end, so instead
+//! of evaluating boards heuristically the solver finds the exact final
+//! difference in piece count. It works directly on the bitboards of the
+//! players, without the bookkeeping of OthelloPosition, and orders moves by:
+//!
+//! * Parity: moves in regions with an odd number of empty squares first,
+//!   since the player moving there is likely to also get the last move.
+//! * Fastest first: moves leaving the opponent with the fewest replies
+//!   first, which quickly narrows the search. Only used while enough
+//!   squares are empty to make the extra move generation worthwhile.
+
+use crate::board::OthelloPosition;
+use crate::move_generator::{flips, move_mask};
+use crate::parity::odd_regions;
+use crate::time_manager::TimeManager;
+
+/// The number of empty squares at or below which the search switches to
+/// the endgame solver by default.
+pub const DEFAULT_ENDGAME_EMPTIES: u32 = 14;
+/// The number of empty squares above which moves are ordered fastest first.
+const FASTEST_FIRST_EMPTIES: u32 = 6;
+/// The number of nodes searched between each check of the time limit.
+/// Must be a power of two.
+const NODES_PER_TIME_CHECK: u64 = 4096;
+/// The greatest possible difference in piece count.
+const MAX_SCORE: isize = 64;
+
+/// The result of a game from the perspective of one of the players.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Searches boards to the end of the game.
+pub struct EndgameSolver<'a> {
+    time: &'a TimeManager,
+    /// The number of boards searched so far.
+    pub nodes: u64,
+}
+
+impl<'a> EndgameSolver<'a> {
+    /// Returns a new solver, which gives up once the hard limit of the
+    /// given TimeManager is reached.
+    pub fn new(time: &'a TimeManager) -> EndgameSolver<'a> {
+        EndgameSolver { time, nodes: 0 }
+    }
+
+    /// Returns the exact final difference in piece count of the board
+    /// with perfect play, from the perspective of the player to go next.
+    /// Returns None if the time limit was reached.
+    pub fn exact_score(&mut self, board: &OthelloPosition) -> Option<isize> {
+        self.solve(board, -MAX_SCORE, MAX_SCORE)
+    }
+
+    /// Returns whether the player to go next wins, draws or loses the board
+    /// with perfect play. Faster than exact_score since the search only
+    /// needs to prove which side of zero the score is on.
+    /// Returns None if the time limit was reached.
+    pub fn outcome(&mut self, board: &OthelloPosition) -> Option<Outcome> {
+        let score = self.solve(board, -1, 1)?;
+        if score > 0 {
+            Some(Outcome::Win)
+        } else if score < 0 {
+            Some(Outcome::Loss)
+        } else {
+            Some(Outcome::Draw)
+        }
+    }
+
+    /// Searches the board to the end of the game within the given window.
+    /// The returned score is exact if it lies strictly inside the window,
+    /// otherwise it is a bound on the same side of the window as the exact score.
+    /// Returns None if the time limit was reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board to solve.
+    /// * `alpha` - The lower end of the window, in pieces.
+    /// * `beta` - The upper end of the window, in pieces.
+    pub fn solve(&mut self, board: &OthelloPosition, alpha: isize, beta: isize) -> Option<isize> {
+        self.negamax(board.player(), board.opponent(), alpha, beta)
+    }
+
+    fn negamax(&mut self, player: u64, opponent: u64, mut alpha: isize, beta: isize) -> Option<isize> {
+        self.nodes += 1;
+        if self.nodes & (NODES_PER_TIME_CHECK - 1) == 0 && self.time.is_out_of_time() {
+            return None;
+        }
+
+        let moves = move_mask(player, opponent);
+        if moves == 0 {
+            if move_mask(opponent, player) == 0 {
+                return Some(player.count_ones() as isize - opponent.count_ones() as isize);
+            }
+            return Some(-self.negamax(opponent, player, -beta, -alpha)?);
+        }
+
+        let mut best = -MAX_SCORE - 1;
+        for square in ordered_moves(player, opponent, moves) {
+            let flipped = flips(player, opponent, square);
+            let new_player = player | flipped | (1 << square);
+            let new_opponent = opponent ^ flipped;
+            let value = -self.negamax(new_opponent, new_player, -beta, -alpha)?;
+            if value > best {
+                best = value;
+            }
+            if best > alpha {
+                alpha = best;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        Some(best)
+    }
+}
+
+/// Returns the squares of the given moves in the order they should be searched.
+fn ordered_moves(player: u64, opponent: u64, mut moves: u64) -> Vec<usize> {
+    let empty = !(player | opponent);
+    let odd_squares = odd_regions(empty);
+    let fastest_first = empty.count_ones() > FASTEST_FIRST_EMPTIES;
+
+    let mut keyed = Vec::with_capacity(moves.count_ones() as usize);
+    while moves != 0 {
+        let square = moves.trailing_zeros() as usize;
+        moves &= moves - 1;
+        let even = odd_squares & (1 << square) == 0;
+        let replies = if fastest_first {
+            let flipped = flips(player, opponent, square);
+            move_mask(opponent ^ flipped, player | flipped | (1 << square)).count_ones()
+        } else {
+            0
+        };
+        keyed.push(((replies, even), square));
+    }
+    keyed.sort_unstable();
+
+    keyed.into_iter().map(|(_, square)| square).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::square_bit;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use std::time::Instant;
+
+    /// Returns a time budget no test comes close to using.
+    fn unlimited() -> TimeManager {
+        TimeManager::new(Instant::now(), 600_000)
+    }
+
+    /// Solves the board by searching every line of play, from the
+    /// perspective of the player to go next.
+    fn brute_force(board: &OthelloPosition) -> isize {
+        let children = board.generate_children();
+        if children.is_empty() {
+            return board.player().count_ones() as isize - board.opponent().count_ones() as isize;
+        }
+        children
+            .iter()
+            .map(|(_, child)| -brute_force(child))
+            .max()
+            .unwrap()
+    }
+
+    /// Returns boards with the given number of empty squares, reached by
+    /// playing random moves from the starting position.
+    fn random_boards(count: usize, empties: u32) -> Vec<OthelloPosition> {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut boards = Vec::new();
+        while boards.len() < count {
+            let mut board = OthelloPosition::start();
+            while board.empty_squares().count_ones() > empties {
+                match board.generate_children().choose(&mut rng) {
+                    Some((_, child)) => board = child.clone(),
+                    None => break,
+                }
+            }
+            if board.empty_squares().count_ones() == empties && !board.is_game_over() {
+                boards.push(board);
+            }
+        }
+
+        boards
+    }
+
+    #[test]
+    fn finished_games_are_scored_by_their_pieces() {
+        let time = unlimited();
+        let mut solver = EndgameSolver::new(&time);
+        // Black has been wiped out, with empty squares left on the board.
+        let wipeout = OthelloPosition::from_colours(square_bit(0, 0) | square_bit(0, 1), 0, false);
+        assert_eq!(solver.exact_score(&wipeout), Some(-2));
+        assert_eq!(solver.outcome(&wipeout), Some(Outcome::Loss));
+        let white = 0x0000_0000_ffff_ffff;
+        let full = OthelloPosition::from_colours(white, !white, true);
+        assert_eq!(solver.exact_score(&full), Some(0));
+        assert_eq!(solver.outcome(&full), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn a_player_without_moves_passes() {
+        let time = unlimited();
+        let mut solver = EndgameSolver::new(&time);
+        // White can not outflank the black corner, so white passes and
+        // black takes the white piece with c1, wiping white out.
+        let board = OthelloPosition::from_colours(square_bit(0, 1), square_bit(0, 0), true);
+        assert_eq!(solver.exact_score(&board), Some(-3));
+        assert_eq!(solver.outcome(&board), Some(Outcome::Loss));
+        assert_eq!(solver.exact_score(&board.pass()), Some(3));
+        assert_eq!(solver.outcome(&board.pass()), Some(Outcome::Win));
+    }
+
+    #[test]
+    fn solving_agrees_with_searching_every_line() {
+        let time = unlimited();
+        let mut solver = EndgameSolver::new(&time);
+        for board in random_boards(6, 8) {
+            let expected = brute_force(&board);
+            assert_eq!(solver.exact_score(&board), Some(expected), "{}", board);
+            let outcome = match expected {
+                0 => Outcome::Draw,
+                score if score > 0 => Outcome::Win,
+                _ => Outcome::Loss,
+            };
+            assert_eq!(solver.outcome(&board), Some(outcome), "{}", board);
+        }
+    }
+
+    #[test]
+    fn a_narrow_window_gives_a_bound_on_the_right_side() {
+        let time = unlimited();
+        let mut solver = EndgameSolver::new(&time);
+        for board in random_boards(4, 9) {
+            let exact = brute_force(&board);
+            let above = solver.solve(&board, exact + 1, exact + 3).unwrap();
+            assert!(exact <= above && above <= exact + 1, "{}", board);
+            let below = solver.solve(&board, exact - 3, exact - 1).unwrap();
+            assert!(exact - 1 <= below && below <= exact, "{}", board);
+            // A null window around the exact score.
+            assert!(solver.solve(&board, exact - 1, exact).unwrap() >= exact);
+            assert!(solver.solve(&board, exact, exact + 1).unwrap() <= exact);
+            assert_eq!(solver.solve(&board, exact - 1, exact + 1), Some(exact));
+        }
+    }
+
+    #[test]
+    fn solving_gives_up_once_out_of_time() {
+        let time = TimeManager::new(Instant::now(), 0);
+        let mut solver = EndgameSolver::new(&time);
+        let board = random_boards(1, 14).remove(0);
+        assert_eq!(solver.exact_score(&board), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! This file contains the Engine, which keeps track of a game for the
+//! long-running front ends. Besides the current board and the boards
+//! before it, the Engine owns the transposition table, so that every
+//! search can make use of the boards searched for earlier moves instead
+//! of starting over.
+
+use crate::alphabeta::{search_moves, search_with_table, SearchConfig, SearchResult};
+use crate::board::OthelloPosition;
+use crate::move_generator::{is_legal, Move};
+use crate::time_manager::TimeManager;
+use crate::transposition::TranspositionTable;
+use std::time::Instant;
+
+/// A game along with the state kept between searches.
+pub struct Engine {
+    board: OthelloPosition,
+    /// The boards before each move played, latest last.
+    history: Vec<OthelloPosition>,
+    table: TranspositionTable,
+    /// The settings of the searches.
+    pub config: SearchConfig,
+}
+
+impl Engine {
+    /// Returns a new Engine at the starting position, with a transposition
+    /// table of the size given by the settings.
+    pub fn new(config: SearchConfig) -> Engine {
+        Engine {
+            board: OthelloPosition::start(),
+            history: Vec::new(),
+            table: TranspositionTable::new(config.table_size),
+            config,
+        }
+    }
+
+    /// Returns the current board.
+    pub fn board(&self) -> &OthelloPosition {
+        &self.board
+    }
+
+    /// Replaces the game with one starting from the given board. The
+    /// transposition table is kept, since its entries do not depend on the game.
+    pub fn set_board(&mut self, board: OthelloPosition) {
+        self.board = board;
+        self.history.clear();
+    }
+
+    /// Plays the move in the current board if it is legal, and returns whether it was.
+    pub fn play(&mut self, p_move: &Move) -> bool {
+        if !is_legal(&self.board, p_move) {
+            return false;
+        }
+        let next = Move::make_move(&self.board, p_move);
+        self.history.push(std::mem::replace(&mut self.board, next));
+        true
+    }
+
+    /// Takes back the last move played, and returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(previous) => {
+                self.board = previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Searches the current board for the given time.
+    pub fn search(&self, time_ms: u64) -> SearchResult {
+        let time = TimeManager::new(Instant::now(), time_ms);
+        search_with_table(&self.board, &time, &self.config, &self.table)
+    }
+
+    /// Searches every legal move of the current board for an exact value,
+    /// splitting the given time between them. See search_moves.
+    pub fn search_moves(&self, time_ms: u64) -> Vec<SearchResult> {
+        search_moves(&self.board, time_ms, &self.config, &self.table)
+    }
+}
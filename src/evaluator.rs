@@ -1,49 +1,214 @@
-/// This file contains the implementation of the struct Evaluator
-/// and its associated functions. These are used for evaluating boards,
-/// where negative values represent situations beneficial for the min player
-/// and positive values represent situations beneficial for the max player.
-/// A value of zero means the advantages of the players is in balance.
+//! This file contains the Evaluate trait implemented by all evaluation
+//! functions, along with the implementation of the struct Evaluator
+//! and its associated functions. These are used for evaluating boards,
+//! where negative values represent situations beneficial for the min player
+//! and positive values represent situations beneficial for the max player.
+//! A value of zero means the advantages of the players is in balance.
 
-use crate::board::{OthelloPosition, EMPTY_CELL, PLAYER_BLACK, PLAYER_WHITE};
+use crate::board::{OthelloPosition, BOARD_SIZE};
 use crate::move_generator;
+use crate::parity::regions;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The weight of each piece of difference on a finished board. Chosen
+/// to be larger than the greatest possible heuristic evaluation.
+pub const FINAL_DISC_WEIGHT: isize = 10_000_000;
+
+/// The greatest magnitude of each aspect of a board before it is weighted.
+const MAX_ASPECT_VALUE: isize = 100;
+
+/// The four corner squares.
+const CORNER_SQUARES: u64 = 0x8100_0000_0000_0081;
+
+/// The squares diagonally and orthogonally adjacent to a corner,
+/// i.e. the X and C squares.
+const CORNER_ADJACENT_SQUARES: u64 = 0x42c3_0000_0000_c342;
+
+/// An evaluation function used by the search to value boards which
+/// are not searched any deeper.
+pub trait Evaluate {
+    /// Evaluates a given board. Returns an integer representing
+    /// which player is deemed to have the advantage and the
+    /// magnitude of that advantage, where positive values favour
+    /// the max player. The magnitude must stay below FINAL_DISC_WEIGHT,
+    /// so that won games are always preferred to heuristic advantages.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - An OthelloPosition representing the board to be evaluated.
+    fn evaluate(&self, board: &OthelloPosition) -> isize;
+}
 
 /// Represents an Evaluator with its associated weights.
 /// Each weight determines how much each aspect taken into
 /// consideration should affect the evaluated value of a given board.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Evaluator {
     pub count_weight: isize,
     pub corners_weight: isize,
     pub imm_mobility_weight: isize,
     pub pot_mobility_weight: isize,
     pub corner_adjacent_weight: isize,
+    pub stability_weight: isize,
+    pub parity_weight: isize,
 }
 
-impl Evaluator {
+impl Evaluate for Evaluator {
+    /// Evaluates a given board as the weighted sum of the
+    /// aspects taken into consideration by the Evaluator.
+    /// Stability and region parity are only computed when they are weighted.
+    fn evaluate(&self, board: &OthelloPosition) -> isize {
+        let mut value = self.count_weight * Evaluator::piece_count_value(board)
+            + self.corners_weight * Evaluator::corners_value(board)
+            + self.imm_mobility_weight * Evaluator::immediate_mobility(board)
+            + self.pot_mobility_weight * Evaluator::potential_mobility(board)
+            + self.corner_adjacent_weight * Evaluator::giving_away_corners(board);
+        if self.stability_weight != 0 {
+            value += self.stability_weight * Evaluator::stability(board);
+        }
+        if self.parity_weight != 0 {
+            value += self.parity_weight * Evaluator::region_parity(board);
+        }
+
+        value
+    }
+}
+
+impl Default for Evaluator {
     /// Returns a new evaluator with weights deemed to
     /// lead to good performance, at least by experimentation.
-    pub fn default() -> Evaluator {
+    /// Stability and region parity are left out, as weighting
+    /// them did not win significantly more games in matches.
+    fn default() -> Evaluator {
         Evaluator {
             count_weight: -100,
             corners_weight: 4000,
             imm_mobility_weight: 400,
             pot_mobility_weight: 600,
             corner_adjacent_weight: 400,
+            stability_weight: 0,
+            parity_weight: 0,
         }
     }
+}
 
-    /// Evaluates a given board. Returns an integer representing
-    /// which player is deemed to have the advantage and the
-    /// magnitude of that advantage.
-    /// 
+/// The reasons a string can fail to describe the weights of an Evaluator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseEvaluatorError {
+    /// The weight is not given as `name=value`. Holds the text found.
+    BadFormat(String),
+    /// There is no weight with the given name.
+    UnknownWeight(String),
+    /// The value of the named weight is not an integer. Holds the name and the value.
+    InvalidValue(String, String),
+    /// The weights are large enough for heuristic values to reach
+    /// FINAL_DISC_WEIGHT. Holds the sum of the absolute weights.
+    WeightsTooLarge(isize),
+}
+
+impl fmt::Display for ParseEvaluatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseEvaluatorError::BadFormat(weight) => {
+                write!(f, "expected a weight such as 'corners=4000' but found '{}'", weight)
+            }
+            ParseEvaluatorError::UnknownWeight(name) => write!(
+                f,
+                "unknown weight '{}', expected one of: {}",
+                name,
+                WEIGHT_NAMES.join(", ")
+            ),
+            ParseEvaluatorError::InvalidValue(name, value) => {
+                write!(f, "expected an integer for '{}' but found '{}'", name, value)
+            }
+            ParseEvaluatorError::WeightsTooLarge(total) => write!(
+                f,
+                "the weights add up to {} in absolute value, which must stay below {}",
+                total,
+                FINAL_DISC_WEIGHT / MAX_ASPECT_VALUE
+            ),
+        }
+    }
+}
+
+impl Error for ParseEvaluatorError {}
+
+/// The names of the weights of an Evaluator, as used when parsing one.
+const WEIGHT_NAMES: [&str; 7] = [
+    "count",
+    "corners",
+    "mobility",
+    "potential_mobility",
+    "corner_adjacent",
+    "stability",
+    "parity",
+];
+
+impl FromStr for Evaluator {
+    type Err = ParseEvaluatorError;
+
+    /// Parses a comma separated list of weights given as `name=value`, such
+    /// as `corners=5000,count=0`. Weights which are not listed keep their
+    /// default values, so the empty string gives the default Evaluator.
+    /// The weights are rejected if they could value a board as highly
+    /// as a finished game, see Evaluate::evaluate.
+    fn from_str(weights: &str) -> Result<Evaluator, ParseEvaluatorError> {
+        let mut evaluator = Evaluator::default();
+        for weight in weights.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+            let (name, value) = match weight.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(ParseEvaluatorError::BadFormat(weight.to_string())),
+            };
+            let field = match name {
+                "count" => &mut evaluator.count_weight,
+                "corners" => &mut evaluator.corners_weight,
+                "mobility" => &mut evaluator.imm_mobility_weight,
+                "potential_mobility" => &mut evaluator.pot_mobility_weight,
+                "corner_adjacent" => &mut evaluator.corner_adjacent_weight,
+                "stability" => &mut evaluator.stability_weight,
+                "parity" => &mut evaluator.parity_weight,
+                _ => return Err(ParseEvaluatorError::UnknownWeight(name.to_string())),
+            };
+            *field = value.parse().map_err(|_| {
+                ParseEvaluatorError::InvalidValue(name.to_string(), value.to_string())
+            })?;
+        }
+        let total = evaluator.total_weight();
+        if total.saturating_mul(MAX_ASPECT_VALUE) >= FINAL_DISC_WEIGHT {
+            return Err(ParseEvaluatorError::WeightsTooLarge(total));
+        }
+
+        Ok(evaluator)
+    }
+}
+
+impl Evaluator {
+    /// Returns the sum of the absolute values of the weights.
+    fn total_weight(&self) -> isize {
+        [
+            self.count_weight,
+            self.corners_weight,
+            self.imm_mobility_weight,
+            self.pot_mobility_weight,
+            self.corner_adjacent_weight,
+            self.stability_weight,
+            self.parity_weight,
+        ]
+        .iter()
+        .fold(0, |total: isize, weight| total.saturating_add(weight.saturating_abs()))
+    }
+
+    /// Evaluates a board where the game is over. The value is the exact
+    /// difference in piece count, scaled so that any won game is valued
+    /// higher than any heuristic evaluation.
+    ///
     /// # Arguments
-    /// 
-    /// * `board` - An OthelloPosition representing the board to be evaluated.
-    pub fn evaluate(&self, board: &OthelloPosition) -> isize {
-        self.count_weight * Evaluator::piece_count_value(board)
-            + self.corners_weight * Evaluator::corners_value(board)
-            + self.imm_mobility_weight * Evaluator::immediate_mobility(board)
-            + self.pot_mobility_weight * Evaluator::potential_mobility(board)
-            + self.corner_adjacent_weight * Evaluator::giving_away_corners(board)
+    ///
+    /// * `board` - An OthelloPosition representing the finished board.
+    pub fn final_score(board: &OthelloPosition) -> isize {
+        FINAL_DISC_WEIGHT * board.disc_difference()
     }
 
     /// Counts the number of pieces belonging to each player.
@@ -54,17 +219,8 @@ impl Evaluator {
     /// 
     /// * `board` - An OthelloPosition representing the board.
     fn piece_count_value(board: &OthelloPosition) -> isize {
-        let mut max_player_coins = 0;
-        let mut min_player_coins = 0;
-        for elem in &board.board {
-            for c in elem {
-                if *c == PLAYER_WHITE {
-                    max_player_coins += 1;
-                } else if *c == PLAYER_BLACK {
-                    min_player_coins += 1;
-                }
-            }
-        }
+        let max_player_coins = board.white().count_ones() as isize;
+        let min_player_coins = board.black().count_ones() as isize;
 
         100 * (max_player_coins - min_player_coins) / (max_player_coins + min_player_coins)
     }
@@ -80,68 +236,8 @@ impl Evaluator {
     /// 
     /// * `board` - An OthelloPosition representing the board
     fn giving_away_corners(board: &OthelloPosition) -> isize {
-        let mut white_value = 0;
-        let mut black_value = 0;
-        match board.board[2][2] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        };
-        match board.board[2][7] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        };
-        match board.board[7][2] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[7][7] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[2][1] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[1][2] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[1][7] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[2][8] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[7][1] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[7][8] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[8][2] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
-        match board.board[8][7] {
-            PLAYER_WHITE => white_value += 1,
-            PLAYER_BLACK => black_value += 1,
-            _ => (),
-        }
+        let white_value = (board.white() & CORNER_ADJACENT_SQUARES).count_ones() as isize;
+        let black_value = (board.black() & CORNER_ADJACENT_SQUARES).count_ones() as isize;
         if white_value + black_value != 0 {
             return -(100 * (white_value - black_value) / (white_value + black_value));
         }
@@ -149,263 +245,159 @@ impl Evaluator {
         0
     }
 
-    /// Counts the number of empty squares next to each piece belonging
-    /// to either player.
-    /// 
+    /// Measures the potential mobility of each player, the empty squares next
+    /// to the pieces of the opponent where moves are likely to open up later.
+    /// Every empty square is counted once for each opponent piece next to it
+    /// in any of the eight directions, so a player with many frontier pieces,
+    /// those next to empty squares, gives the other player a high potential
+    /// mobility. Returns a positive value if the max player has the higher
+    /// potential mobility and a negative value if the min player has.
+    ///
     /// # Arguments
-    /// 
+    ///
     /// * `board` - An OthelloPosition representing the board.
     fn potential_mobility(board: &OthelloPosition) -> isize {
-        let mut white_count = 0;
-        let mut black_count = 0;
-        for row in 1..=8 {
-            for col in 1..=8 {
-                if board.board[row][col] == PLAYER_BLACK {
-                    let above = board.board[row - 1][col];
-                    let below = board.board[row - 1][col];
-                    let right = board.board[row][col + 1];
-                    let left = board.board[row][col - 1];
-                    let top_right = board.board[row - 1][col + 1];
-                    let top_left = board.board[row - 1][col - 1];
-                    let bot_right = board.board[row + 1][col + 1];
-                    let bot_left = board.board[row + 1][col - 1];
-                    if row == 1 {
-                        // Can't look up
-                        if col == 1 {
-                            // Can't look left
-                            if right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        } else if col == 8 {
-                            // Can't look right
-                            if left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        } else {
-                            if left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        }
-                    } else if row == 8 {
-                        // Can't look down
-                        if col == 1 {
-                            // Can't look left
-                            if above == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if top_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        } else if col == 8 {
-                            // Can't look right
-                            if above == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if top_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        } else {
-                            if top_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if top_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        }
-                    } else {
-                        // Can look both up and down
-                        if above == EMPTY_CELL {
-                            white_count += 1
-                        };
-                        if below == EMPTY_CELL {
-                            white_count += 1
-                        };
-                        if col == 1 {
-                            // Cannot look left
-                            if top_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        } else if col == 8 {
-                            // Cannot look right
-                            if top_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                white_count += 1
-                            };
-                        }
-                    }
-                } else if board.board[row][col] == PLAYER_WHITE {
-                    let above = board.board[row - 1][col];
-                    let below = board.board[row - 1][col];
-                    let right = board.board[row][col + 1];
-                    let left = board.board[row][col - 1];
-                    let top_right = board.board[row - 1][col + 1];
-                    let top_left = board.board[row - 1][col - 1];
-                    let bot_right = board.board[row + 1][col + 1];
-                    let bot_left = board.board[row + 1][col - 1];
-                    if row == 1 {
-                        // Can't look up
-                        if col == 1 {
-                            // Can't look left
-                            if right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        } else if col == 8 {
-                            // Can't look right
-                            if left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        } else {
-                            if left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if below == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        }
-                    } else if row == 8 {
-                        // Can't look down
-                        if col == 1 {
-                            // Can't look left
-                            if above == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if top_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        } else if col == 8 {
-                            // Can't look right
-                            if above == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if top_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        } else {
-                            if top_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if top_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        }
-                    } else {
-                        // Can look both up and down
-                        if above == EMPTY_CELL {
-                            black_count += 1
-                        };
-                        if below == EMPTY_CELL {
-                            black_count += 1
-                        };
-                        if col == 1 {
-                            // Cannot look left
-                            if top_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_right == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        } else if col == 8 {
-                            // Cannot look right
-                            if top_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                            if bot_left == EMPTY_CELL {
-                                black_count += 1
-                            };
-                        }
-                    }
-                }
+        let empty = board.empty_squares();
+        let max_potential = Evaluator::exposed_squares(board.black(), empty);
+        let min_potential = Evaluator::exposed_squares(board.white(), empty);
+
+        if max_potential + min_potential != 0 {
+            return 100 * (max_potential - min_potential) / (max_potential + min_potential);
+        }
+
+        0
+    }
+
+    /// Counts the pairs of a piece and an empty square next to it, in any of
+    /// the eight directions, among the given pieces.
+    ///
+    /// # Arguments
+    ///
+    /// * `pieces` - The pieces of a player.
+    /// * `empty` - The empty squares of the board.
+    fn exposed_squares(pieces: u64, empty: u64) -> isize {
+        move_generator::neighbours(pieces)
+            .iter()
+            .map(|squares| (squares & empty).count_ones() as isize)
+            .sum()
+    }
+
+    /// Counts the number of stable pieces of each player, pieces which can
+    /// never be flipped for the rest of the game. Returns a positive value
+    /// if the max player has more stable pieces and a negative value if the
+    /// min player has more.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - An OthelloPosition representing the board.
+    fn stability(board: &OthelloPosition) -> isize {
+        let (white, black) = (board.white(), board.black());
+        let max_stable = Evaluator::stable_pieces(white, black).count_ones() as isize;
+        let min_stable = Evaluator::stable_pieces(black, white).count_ones() as isize;
+
+        if max_stable + min_stable != 0 {
+            return 100 * (max_stable - min_stable) / (max_stable + min_stable);
+        }
+
+        0
+    }
+
+    /// Returns a bitboard of the pieces of `player` which are known to be
+    /// stable. A piece can only be flipped along a line through it, so it
+    /// is stable once it is protected along each of the four lines through
+    /// it: either the line is full, leaving no square to move to, or the
+    /// piece is next to the edge of the board or to a stable piece of its
+    /// own on that line. Starting from the corners, which are always stable,
+    /// the stable pieces grow along the edges and inwards until no more are
+    /// found. Some stable pieces may be missed, but none are counted wrongly.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The pieces of the player.
+    /// * `opponent` - The pieces of the opponent.
+    fn stable_pieces(player: u64, opponent: u64) -> u64 {
+        let filled = player | opponent;
+        // For each of the four lines through a square, given by a direction
+        // and its opposite, the squares from which the line is full.
+        let full_lines = [0, 1, 2, 3].map(|direction| {
+            Evaluator::full_towards(filled, direction)
+                & Evaluator::full_towards(filled, direction + 4)
+        });
+
+        let mut stable = 0;
+        loop {
+            let mut protected = player;
+            for (direction, full) in full_lines.iter().enumerate() {
+                protected &= full
+                    | Evaluator::next_to(stable, direction)
+                    | Evaluator::next_to(stable, direction + 4);
+            }
+            if protected == stable {
+                return stable;
+            }
+            stable = protected;
+        }
+    }
+
+    /// Returns a bitboard of the filled squares from which every square in
+    /// the given direction up to the edge of the board is filled.
+    ///
+    /// # Arguments
+    ///
+    /// * `filled` - The squares holding a piece.
+    /// * `direction` - The number of the direction, as used by move_generator::step.
+    fn full_towards(filled: u64, direction: usize) -> u64 {
+        let mut full = filled;
+        for _ in 1..BOARD_SIZE {
+            full = filled & Evaluator::next_to(full, direction);
+        }
+
+        full
+    }
+
+    /// Returns a bitboard of the squares whose neighbour in the given
+    /// direction is one of the given squares or lies outside the board.
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` - The squares to look for.
+    /// * `direction` - The number of the direction, as used by move_generator::step.
+    fn next_to(squares: u64, direction: usize) -> u64 {
+        let opposite = (direction + 4) % 8;
+        move_generator::step(squares, opposite) | !move_generator::step(u64::MAX, opposite)
+    }
+
+    /// Counts the empty regions with an odd number of squares in which each
+    /// player is expected to get the last move, by being the first to move
+    /// there: the player to go next in the regions where they have a legal
+    /// move, and the other player in the rest of the regions where they have
+    /// one. Returns a positive value if the max player gets the last move in
+    /// more odd regions and a negative value if the min player does.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - An OthelloPosition representing the board.
+    fn region_parity(board: &OthelloPosition) -> isize {
+        let player_moves = move_generator::move_mask(board.player(), board.opponent());
+        let opponent_moves = move_generator::move_mask(board.opponent(), board.player());
+        let (mut player_regions, mut opponent_regions) = (0, 0);
+        for region in regions(board.empty_squares()).filter(|r| r.count_ones() % 2 == 1) {
+            if region & player_moves != 0 {
+                player_regions += 1;
+            } else if region & opponent_moves != 0 {
+                opponent_regions += 1;
             }
         }
-        if white_count + black_count != 0 {
-            return 100 * (white_count - black_count) / (white_count + black_count);
+        let (max_regions, min_regions) = if board.max_player() {
+            (player_regions, opponent_regions)
+        } else {
+            (opponent_regions, player_regions)
+        };
+
+        if max_regions + min_regions != 0 {
+            return 100 * (max_regions - min_regions) / (max_regions + min_regions);
         }
+
         0
     }
 
@@ -416,31 +408,13 @@ impl Evaluator {
     /// 
     /// * `board` - An OthelloPosition representing the board.
     fn immediate_mobility(board: &OthelloPosition) -> isize {
-        let num_max_moves;
-        let num_min_moves;
-        if board.max_player {
-            let min_board = OthelloPosition {
-                board: board.board,
-                max_player: false,
-                score: 0,
-            };
-            let max_board = board;
-            num_max_moves = move_generator::get_moves(max_board).len() as isize;
-            num_min_moves = move_generator::get_moves(&min_board).len() as isize;
-        } else {
-            let max_board = OthelloPosition {
-                board: board.board,
-                max_player: true,
-                score: 0,
-            };
-            let min_board = board;
-            num_max_moves = move_generator::get_moves(&max_board).len() as isize;
-            num_min_moves = move_generator::get_moves(min_board).len() as isize;
-        }
+        let white = board.white();
+        let black = board.black();
+        let num_max_moves = move_generator::move_mask(white, black).count_ones() as isize;
+        let num_min_moves = move_generator::move_mask(black, white).count_ones() as isize;
 
         if num_max_moves + num_min_moves != 0 {
-            return (100 * (num_max_moves - num_min_moves) / (num_max_moves + num_min_moves))
-                as isize;
+            return 100 * (num_max_moves - num_min_moves) / (num_max_moves + num_min_moves);
         }
 
         0
@@ -456,39 +430,187 @@ impl Evaluator {
     /// 
     /// * `board` - An OthelloPosition representing the board.
     fn corners_value(board: &OthelloPosition) -> isize {
-        let mut max_corners = 0;
-        let mut min_corners = 0;
-        let cells = &board.board;
-        let top_left = cells[1][1];
-        let top_right = cells[1][8];
-        let bottom_left = cells[8][1];
-        let bottom_right = cells[8][8];
-
-        match top_left {
-            PLAYER_WHITE => max_corners += 1,
-            PLAYER_BLACK => min_corners += 1,
-            _ => (),
-        }
-        match top_right {
-            PLAYER_WHITE => max_corners += 1,
-            PLAYER_BLACK => min_corners += 1,
-            _ => (),
-        }
-        match bottom_left {
-            PLAYER_WHITE => max_corners += 1,
-            PLAYER_BLACK => min_corners += 1,
-            _ => (),
-        }
-        match bottom_right {
-            PLAYER_WHITE => max_corners += 1,
-            PLAYER_BLACK => min_corners += 1,
-            _ => (),
-        }
+        let max_corners = (board.white() & CORNER_SQUARES).count_ones() as isize;
+        let min_corners = (board.black() & CORNER_SQUARES).count_ones() as isize;
 
         if max_corners + min_corners != 0 {
-            return (100 * (max_corners - min_corners) / (max_corners + min_corners)) as isize;
+            return 100 * (max_corners - min_corners) / (max_corners + min_corners);
         }
 
         0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::square_bit;
+
+    #[test]
+    fn weights_are_parsed_over_the_defaults() {
+        let evaluator: Evaluator = "corners=5000, count=0".parse().unwrap();
+        assert_eq!(evaluator.corners_weight, 5000);
+        assert_eq!(evaluator.count_weight, 0);
+        assert_eq!(evaluator.imm_mobility_weight, Evaluator::default().imm_mobility_weight);
+        assert_eq!("".parse(), Ok(Evaluator::default()));
+    }
+
+    #[test]
+    fn weights_valuing_boards_like_finished_games_are_rejected() {
+        let huge = "corners=100000000".parse::<Evaluator>();
+        assert!(matches!(huge, Err(ParseEvaluatorError::WeightsTooLarge(_))));
+        // Negative weights count towards the total as well.
+        let negative = format!("count={}", -FINAL_DISC_WEIGHT / MAX_ASPECT_VALUE);
+        assert!(negative.parse::<Evaluator>().is_err());
+        let overflowing = format!("corners={},count={}", isize::MAX, isize::MIN);
+        assert!(overflowing.parse::<Evaluator>().is_err());
+        assert!("corners=90000".parse::<Evaluator>().is_ok());
+    }
+
+    #[test]
+    fn exposed_squares_counts_all_eight_neighbours() {
+        let empty = !square_bit(3, 3);
+        assert_eq!(Evaluator::exposed_squares(square_bit(3, 3), empty), 8);
+    }
+
+    #[test]
+    fn exposed_squares_stays_on_the_board() {
+        let corner = square_bit(0, 0);
+        assert_eq!(Evaluator::exposed_squares(corner, !corner), 3);
+        let edge = square_bit(3, 0);
+        assert_eq!(Evaluator::exposed_squares(edge, !edge), 5);
+        let far_edge = square_bit(7, 4);
+        assert_eq!(Evaluator::exposed_squares(far_edge, !far_edge), 5);
+    }
+
+    #[test]
+    fn exposed_squares_skips_occupied_neighbours() {
+        // A column of three pieces, where the middle one is covered above
+        // and below and the others on one side each.
+        let pieces = square_bit(2, 3) | square_bit(3, 3) | square_bit(4, 3);
+        assert_eq!(Evaluator::exposed_squares(square_bit(3, 3), !pieces), 6);
+        assert_eq!(Evaluator::exposed_squares(pieces, !pieces), 7 + 6 + 7);
+        // A piece whose only empty neighbour is below it.
+        let empty = square_bit(7, 2);
+        assert_eq!(Evaluator::exposed_squares(square_bit(6, 2), empty), 1);
+        // The same empty square next to two pieces counts for both.
+        let pair = square_bit(6, 1) | square_bit(6, 3);
+        assert_eq!(Evaluator::exposed_squares(pair, empty), 2);
+    }
+
+    #[test]
+    fn potential_mobility_favours_the_player_facing_more_frontier() {
+        // A black piece in the middle faces white with eight empty squares,
+        // while a white piece in the corner faces black with three.
+        let board = OthelloPosition::from_colours(square_bit(0, 0), square_bit(3, 3), true);
+        assert_eq!(Evaluator::potential_mobility(&board), 100 * (8 - 3) / (8 + 3));
+        let swapped = OthelloPosition::from_colours(square_bit(3, 3), square_bit(0, 0), true);
+        assert_eq!(Evaluator::potential_mobility(&swapped), -100 * (8 - 3) / (8 + 3));
+    }
+
+    #[test]
+    fn potential_mobility_is_balanced_in_the_starting_position() {
+        assert_eq!(Evaluator::potential_mobility(&OthelloPosition::start()), 0);
+    }
+
+    #[test]
+    fn potential_mobility_is_zero_on_a_full_board() {
+        let white = 0x0000_0000_ffff_ffff;
+        let board = OthelloPosition::from_colours(white, !white, true);
+        assert_eq!(Evaluator::potential_mobility(&board), 0);
+    }
+
+    #[test]
+    fn a_lone_corner_is_stable() {
+        let corner = square_bit(0, 7);
+        assert_eq!(Evaluator::stable_pieces(corner, 0), corner);
+        let edge = square_bit(0, 3);
+        assert_eq!(Evaluator::stable_pieces(edge, 0), 0);
+    }
+
+    #[test]
+    fn stability_grows_along_the_edge_from_a_corner() {
+        let run = square_bit(0, 0) | square_bit(0, 1) | square_bit(0, 2);
+        assert_eq!(Evaluator::stable_pieces(run, 0), run);
+        // The same run without the corner can be captured from both sides.
+        let detached = square_bit(0, 1) | square_bit(0, 2) | square_bit(0, 3);
+        assert_eq!(Evaluator::stable_pieces(detached, 0), 0);
+    }
+
+    #[test]
+    fn an_opponent_piece_stops_stability_along_the_edge() {
+        let white = square_bit(0, 0) | square_bit(0, 2);
+        let black = square_bit(0, 1);
+        assert_eq!(Evaluator::stable_pieces(white, black), square_bit(0, 0));
+        assert_eq!(Evaluator::stable_pieces(black, white), 0);
+    }
+
+    #[test]
+    fn a_piece_off_the_edge_needs_protection_on_every_line() {
+        // The piece diagonally next to the corner can still be flipped along
+        // the diagonal between the two empty squares beside it.
+        let block = square_bit(0, 0) | square_bit(0, 1) | square_bit(1, 0) | square_bit(1, 1);
+        assert_eq!(Evaluator::stable_pieces(block, 0), block & !square_bit(1, 1));
+        let covered = block | square_bit(0, 2) | square_bit(2, 0);
+        assert_eq!(Evaluator::stable_pieces(covered, 0), covered);
+    }
+
+    #[test]
+    fn a_full_edge_is_stable_for_both_players() {
+        let white = 0x55;
+        let black = 0xaa;
+        assert_eq!(Evaluator::stable_pieces(white, black), white);
+        assert_eq!(Evaluator::stable_pieces(black, white), black);
+    }
+
+    #[test]
+    fn a_full_line_alone_is_not_enough() {
+        // A full row in the middle can still be flipped vertically and diagonally.
+        let row = 0xff << 24;
+        assert_eq!(Evaluator::stable_pieces(row, 0), 0);
+    }
+
+    #[test]
+    fn every_piece_of_a_full_board_is_stable() {
+        let white = 0x0f0f_0f0f_f0f0_f0f0;
+        assert_eq!(Evaluator::stable_pieces(white, !white), white);
+        assert_eq!(Evaluator::stable_pieces(!white, white), !white);
+    }
+
+    #[test]
+    fn stability_compares_the_stable_pieces_of_both_players() {
+        let white = square_bit(0, 0) | square_bit(0, 1) | square_bit(7, 7);
+        let black = square_bit(7, 0) | square_bit(3, 3);
+        let board = OthelloPosition::from_colours(white, black, true);
+        assert_eq!(Evaluator::stability(&board), 100 * (3 - 1) / (3 + 1));
+        assert_eq!(Evaluator::stability(&OthelloPosition::start()), 0);
+    }
+
+    #[test]
+    fn region_parity_goes_to_the_first_player_able_to_move() {
+        // Only white can move in the lone empty corner, whoever is to go next.
+        let black = square_bit(0, 1);
+        let white = !(black | square_bit(0, 0));
+        for max_player in [true, false] {
+            let board = OthelloPosition::from_colours(white, black, max_player);
+            assert_eq!(Evaluator::region_parity(&board), 100);
+        }
+        // Black can only move in the other corner.
+        let black = square_bit(7, 5);
+        let white = !(black | square_bit(7, 7));
+        let board = OthelloPosition::from_colours(white, black, true);
+        assert_eq!(Evaluator::region_parity(&board), -100);
+    }
+
+    #[test]
+    fn region_parity_only_counts_odd_regions() {
+        let black = square_bit(0, 1) | square_bit(7, 5);
+        let white = !(black | square_bit(0, 0) | square_bit(7, 7));
+        let board = OthelloPosition::from_colours(white, black, true);
+        assert_eq!(Evaluator::region_parity(&board), 0);
+        let black = square_bit(0, 2);
+        let white = !(black | square_bit(0, 0) | square_bit(0, 1));
+        let board = OthelloPosition::from_colours(white, black, true);
+        assert_eq!(Evaluator::region_parity(&board), 0);
+    }
+}
@@ -0,0 +1,155 @@
+//! This file contains the adapter letting an external executable take part
+//! in a match, as long as it follows the same contract as the binary of this
+//! crate: it is run once per move with the position string and the time
+//! limit in seconds as its arguments, and prints its move as the one-based
+//! `(row,col)`, in algebraic notation, or `pass`. This allows playing against
+//! earlier builds of the engine, or against other engines entirely.
+//!
+//! Since earlier builds can not parse fractions of a second, the time limit
+//! is always rounded up to a whole number of seconds, and is at least one.
+//! An executable which does not exit within its time limit plus a margin is
+//! killed, and like one printing something which is not a move, forfeits.
+
+use crate::board::{OthelloPosition, BOARD_SIZE};
+use crate::match_runner::Player;
+use crate::move_generator::Move;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The time in milliseconds an executable may run past its time limit by default.
+pub const DEFAULT_TIMEOUT_MARGIN_MS: u64 = 1000;
+/// The time between each check of whether the executable has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A player running an external executable for every move.
+pub struct ExternalPlayer {
+    name: String,
+    program: String,
+    args: Vec<String>,
+    move_time_ms: u64,
+    timeout_margin_ms: u64,
+}
+
+impl ExternalPlayer {
+    /// Returns a new ExternalPlayer named after the executable.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The path of the executable.
+    /// * `args` - Arguments given to the executable before the position and time limit.
+    /// * `move_time_ms` - The time limit given to the executable for each move, in milliseconds,
+    ///   which is rounded up to whole seconds.
+    /// * `timeout_margin_ms` - The time the executable may run past its time limit before forfeiting.
+    pub fn new(
+        program: &str,
+        args: Vec<String>,
+        move_time_ms: u64,
+        timeout_margin_ms: u64,
+    ) -> ExternalPlayer {
+        let name = Path::new(program)
+            .file_name()
+            .map_or(program.to_string(), |name| name.to_string_lossy().into_owned());
+        ExternalPlayer {
+            name,
+            program: program.to_string(),
+            args,
+            move_time_ms: move_time_ms.div_ceil(1000).max(1) * 1000,
+            timeout_margin_ms,
+        }
+    }
+
+    /// Sets the name of the player, as written in the transcript.
+    pub fn with_name(mut self, name: &str) -> ExternalPlayer {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Returns the time limit as given to the executable, a whole number of seconds.
+    fn time_limit(&self) -> String {
+        (self.move_time_ms / 1000).to_string()
+    }
+}
+
+impl Player for ExternalPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose_move(&mut self, board: &OthelloPosition) -> io::Result<Move> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(board.to_string())
+            .arg(self.time_limit())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let timeout = Duration::from_millis(self.move_time_ms + self.timeout_margin_ms);
+        wait_with_timeout(&mut child, timeout)?;
+
+        let mut output = String::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout.read_to_string(&mut output)?;
+        }
+        parse_reply(&output, board.to_move())
+    }
+}
+
+/// Waits for the child to exit, killing it if it has not exited once
+/// the timeout has passed.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> io::Result<()> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return if status.success() {
+                Ok(())
+            } else {
+                Err(io::Error::other(format!("the engine exited with {}", status)))
+            };
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no move within {:.3}s", timeout.as_secs_f64()),
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Parses the move printed by an executable, given as the one-based
+/// `(row,col)`, in algebraic notation or as `pass`.
+///
+/// # Arguments
+///
+/// * `reply` - The output of the executable.
+/// * `player` - a char representation of the colour of the player making the move.
+pub fn parse_reply(reply: &str, player: char) -> io::Result<Move> {
+    let reply = reply.trim();
+    let tuple = reply
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .and_then(|rest| rest.split_once(','));
+    let on_board = |index: usize| (1..=BOARD_SIZE).contains(&index);
+    let parsed = match tuple {
+        Some((row, col)) => match (row.trim().parse(), col.trim().parse()) {
+            (Ok(row), Ok(col)) if on_board(row) && on_board(col) => {
+                Some(Move::new(player, row - 1, col - 1))
+            }
+            _ => None,
+        },
+        None => Move::parse(reply, player).ok(),
+    };
+
+    parsed.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unreadable move '{}'", reply),
+        )
+    })
+}
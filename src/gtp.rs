@@ -0,0 +1,217 @@
+//! This file contains a line protocol modelled on the Go Text Protocol
+//! (GTP), for scripts driving the engine as a long-running process. Every
+//! command is a single line, optionally preceded by a numeric id, and every
+//! answer starts with `=` on success or `?` on failure, followed by the id,
+//! the response and an empty line. Since the process keeps running, the
+//! transposition table is kept from one search to the next.
+//!
+//! Boards are given as position strings and moves in algebraic notation.
+//! Values are given in pieces from the perspective of the player to move.
+
+use crate::alphabeta::{SearchConfig, SearchResult};
+use crate::board::OthelloPosition;
+use crate::engine::Engine;
+use crate::interactive::render;
+use crate::move_generator::{get_moves, Move};
+use std::io::{self, BufRead, Write};
+
+/// The commands understood, as listed by `list_commands`.
+const COMMANDS: [&str; 9] = [
+    "setboard",
+    "play",
+    "undo",
+    "genmove",
+    "analyze",
+    "showboard",
+    "time",
+    "list_commands",
+    "quit",
+];
+
+/// Answers commands read from `input` by writing to `output`, until the
+/// input ends or `quit` is received. The game starts from the starting position.
+///
+/// # Arguments
+///
+/// * `input` - The commands to answer.
+/// * `output` - Where the answers are written.
+/// * `config` - The settings of the searches.
+/// * `move_time_ms` - The time in milliseconds spent on each `genmove` or `analyze`,
+///   until changed by `time`.
+pub fn run<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    config: SearchConfig,
+    mut move_time_ms: u64,
+) -> io::Result<()> {
+    let mut engine = Engine::new(config);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        let id = match words.first() {
+            Some(word) if word.chars().all(|c| c.is_ascii_digit()) => words.remove(0),
+            _ => "",
+        };
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => continue,
+        };
+
+        let response = match command {
+            "setboard" => match args {
+                [position] => position
+                    .parse()
+                    .map(|board| {
+                        engine.set_board(board);
+                        String::new()
+                    })
+                    .map_err(|e| format!("invalid position: {}", e)),
+                _ => Err(String::from("expected a position")),
+            },
+            "play" => play(&mut engine, args),
+            "undo" => {
+                if engine.undo() {
+                    Ok(String::new())
+                } else {
+                    Err(String::from("cannot undo"))
+                }
+            }
+            "genmove" => check_colour(engine.board(), args).and_then(|_| {
+                if engine.board().is_game_over() {
+                    return Err(String::from("the game is over"));
+                }
+                let result = engine.search(move_time_ms);
+                let p_move = result
+                    .best_move
+                    .unwrap_or_else(|| Move::pass(engine.board().to_move()));
+                engine.play(&p_move);
+                Ok(p_move.to_string())
+            }),
+            "analyze" => analyze(&engine, args, move_time_ms),
+            "showboard" => {
+                let mut board = Vec::new();
+                render(&mut board, engine.board(), &get_moves(engine.board()))?;
+                Ok(String::from_utf8_lossy(&board).trim_end().to_string())
+            }
+            "time" => match args {
+                [seconds] => match seconds.parse::<f64>() {
+                    Ok(parsed) if parsed.is_finite() && parsed >= 0.0 => {
+                        move_time_ms = (parsed * 1000.0) as u64;
+                        Ok(String::new())
+                    }
+                    _ => Err(format!("invalid time '{}'", seconds)),
+                },
+                _ => Err(String::from("expected a time in seconds")),
+            },
+            "list_commands" => Ok(COMMANDS.join("\n")),
+            "quit" => {
+                respond(output, id, Ok(String::new()))?;
+                return Ok(());
+            }
+            _ => Err(String::from("unknown command")),
+        };
+        respond(output, id, response)?;
+    }
+}
+
+/// Writes an answer, `=` followed by the response on success and `?`
+/// followed by the error on failure, ended by an empty line.
+fn respond<W: Write>(output: &mut W, id: &str, response: Result<String, String>) -> io::Result<()> {
+    let (status, text) = match response {
+        Ok(text) => ('=', text),
+        Err(text) => ('?', text),
+    };
+    if text.is_empty() {
+        write!(output, "{}{}\n\n", status, id)?;
+    } else {
+        write!(output, "{}{} {}\n\n", status, id, text)?;
+    }
+    output.flush()
+}
+
+/// Handles `play`, which is given a move optionally preceded by the
+/// colour of the player making it.
+fn play(engine: &mut Engine, args: &[&str]) -> Result<String, String> {
+    let notation = match args {
+        [notation] => notation,
+        [_, notation] => {
+            check_colour(engine.board(), &args[..1])?;
+            notation
+        }
+        _ => return Err(String::from("expected a move")),
+    };
+    let p_move = Move::parse(notation, engine.board().to_move())
+        .map_err(|e| format!("invalid move: {}", e))?;
+    if engine.play(&p_move) {
+        Ok(String::new())
+    } else {
+        Err(format!("illegal move {}", p_move))
+    }
+}
+
+/// Checks that the colour given as the only argument, if any,
+/// is the colour of the player to go next.
+fn check_colour(board: &OthelloPosition, args: &[&str]) -> Result<(), String> {
+    let colour = match args {
+        [] => return Ok(()),
+        [colour] => colour.to_ascii_lowercase(),
+        _ => return Err(String::from("expected at most a colour")),
+    };
+    let white = match colour.as_str() {
+        "w" | "white" => true,
+        "b" | "black" => false,
+        _ => return Err(format!("invalid colour '{}'", colour)),
+    };
+    if white == board.max_player() {
+        Ok(())
+    } else if board.max_player() {
+        Err(String::from("it is white's turn"))
+    } else {
+        Err(String::from("it is black's turn"))
+    }
+}
+
+/// Handles `analyze`, which searches the current board and answers with
+/// the best move, its value, depth, node count and principal variation.
+/// Given a count, answers with a line like that for each of that many of
+/// the best moves, searched to exact values.
+fn analyze(engine: &Engine, args: &[&str], move_time_ms: u64) -> Result<String, String> {
+    let results = match args {
+        [] => vec![engine.search(move_time_ms)],
+        [count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => {
+                let mut results = engine.search_moves(move_time_ms);
+                results.truncate(count);
+                results
+            }
+            _ => return Err(format!("invalid move count '{}'", count)),
+        },
+        _ => return Err(String::from("expected at most a move count")),
+    };
+
+    Ok(results
+        .iter()
+        .map(format_analysis)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Returns a line describing the result of a search.
+fn format_analysis(result: &SearchResult) -> String {
+    let p_move = result
+        .best_move
+        .map_or(String::from("pass"), |p_move| p_move.to_string());
+    let pv: Vec<String> = result.pv.iter().map(Move::to_string).collect();
+    format!(
+        "{} score {:+.2} depth {} nodes {} pv {}",
+        p_move,
+        result.score_in_discs(),
+        result.depth,
+        result.nodes,
+        pv.join(" ")
+    )
+}
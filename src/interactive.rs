@@ -0,0 +1,178 @@
+//! This file contains the interactive mode, in which a human plays a game
+//! against the engine from the starting position. The board is shown as
+//! text with the columns a-h and rows 1-8 marked, along with the legal
+//! moves of the human, who enters moves in algebraic notation. Moves can be
+//! taken back with `undo`, which returns to the previous move of the human.
+
+use crate::alphabeta::{alphabeta_move_gen, SearchConfig};
+use crate::board::{OthelloPosition, BOARD_SIZE, EMPTY_CELL, PLAYER_BLACK, PLAYER_WHITE};
+use crate::move_generator::{get_moves, Move};
+use crate::time_manager::TimeManager;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+/// The char marking the squares the human can play on.
+const LEGAL_MOVE_MARK: char = '*';
+/// The char shown for empty squares.
+const EMPTY_MARK: char = '.';
+
+const HELP: &str = "Commands:
+    <move>  play a move, such as d3
+    moves   list your legal moves
+    undo    take back your last move and the reply of the engine
+    help    show this message
+    quit    stop playing";
+
+/// Plays a game between a human and the engine, reading the moves of the
+/// human from `input` and writing the board and the moves of the engine to
+/// `output`. Returns once the game is over, the human quits or the input ends.
+///
+/// # Arguments
+///
+/// * `input` - The source of the commands of the human.
+/// * `output` - Where the board and messages are written.
+/// * `human_white` - Whether the human plays white, which moves second.
+/// * `move_time_ms` - The time in milliseconds the engine may spend on each move.
+/// * `config` - The settings of the searches of the engine.
+pub fn play<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    human_white: bool,
+    move_time_ms: u64,
+    config: &SearchConfig,
+) -> io::Result<()> {
+    let mut board = OthelloPosition::start();
+    // The boards in which the human made a move, latest last.
+    let mut history: Vec<OthelloPosition> = Vec::new();
+    // The board is only shown again once it has changed.
+    let mut show_board = true;
+    writeln!(output, "{}", HELP)?;
+
+    loop {
+        if board.is_game_over() {
+            render(output, &board, &[])?;
+            return write_result(output, &board);
+        }
+
+        if board.max_player() != human_white {
+            let time = TimeManager::new(Instant::now(), move_time_ms);
+            let p_move = alphabeta_move_gen(&board, &time, config)
+                .unwrap_or_else(|| Move::pass(board.to_move()));
+            writeln!(output, "Engine plays {}", p_move)?;
+            board = Move::make_move(&board, &p_move);
+            show_board = true;
+            continue;
+        }
+
+        let moves = get_moves(&board);
+        if show_board {
+            render(output, &board, &moves)?;
+            show_board = false;
+        }
+        if moves.is_empty() {
+            writeln!(output, "You have no legal moves and have to pass.")?;
+            board = board.pass();
+            show_board = true;
+            continue;
+        }
+
+        write!(output, "Your move: ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => return Ok(()),
+            "help" => writeln!(output, "{}", HELP)?,
+            "moves" => writeln!(output, "Legal moves: {}", list_moves(&moves))?,
+            "undo" => match history.pop() {
+                Some(previous) => {
+                    board = previous;
+                    show_board = true;
+                }
+                None => writeln!(output, "There is no move to undo.")?,
+            },
+            command => match Move::parse(command, board.to_move()) {
+                Ok(p_move) if moves.contains(&p_move) => {
+                    history.push(board.clone());
+                    board = Move::make_move(&board, &p_move);
+                    show_board = true;
+                }
+                Ok(p_move) if p_move.is_pass() => {
+                    writeln!(output, "You can only pass without legal moves.")?
+                }
+                Ok(p_move) => writeln!(
+                    output,
+                    "{} is not a legal move, try one of: {}",
+                    p_move,
+                    list_moves(&moves)
+                )?,
+                Err(e) => writeln!(output, "Invalid move '{}': {}", command, e)?,
+            },
+        }
+    }
+}
+
+/// Writes the board with the columns and rows marked, followed by the
+/// piece counts and the player to go next. The given moves are marked
+/// on their squares.
+///
+/// # Arguments
+///
+/// * `output` - Where the board is written.
+/// * `board` - The board to show.
+/// * `moves` - The moves to mark on the board.
+pub fn render<W: Write>(output: &mut W, board: &OthelloPosition, moves: &[Move]) -> io::Result<()> {
+    write!(output, "\n ")?;
+    for col in 0..BOARD_SIZE {
+        write!(output, " {}", (b'a' + col as u8) as char)?;
+    }
+    writeln!(output)?;
+    for row in 0..BOARD_SIZE {
+        write!(output, "{}", row + 1)?;
+        for col in 0..BOARD_SIZE {
+            let mark = match board.cell(row + 1, col + 1) {
+                EMPTY_CELL if moves.iter().any(|m| m.row == row && m.col == col) => {
+                    LEGAL_MOVE_MARK
+                }
+                EMPTY_CELL => EMPTY_MARK,
+                piece => piece,
+            };
+            write!(output, " {}", mark)?;
+        }
+        writeln!(output)?;
+    }
+    writeln!(
+        output,
+        "Black ({}): {}  White ({}): {}  {} to move",
+        PLAYER_BLACK,
+        board.black().count_ones(),
+        PLAYER_WHITE,
+        board.white().count_ones(),
+        if board.max_player() { "White" } else { "Black" }
+    )
+}
+
+/// Writes the final piece counts and the winner of a finished game.
+fn write_result<W: Write>(output: &mut W, board: &OthelloPosition) -> io::Result<()> {
+    let (white, black) = (board.white().count_ones(), board.black().count_ones());
+    let winner = if white > black {
+        "White wins"
+    } else if black > white {
+        "Black wins"
+    } else {
+        "The game is drawn"
+    };
+    writeln!(output, "Game over. {} {}-{}.", winner, black.max(white), black.min(white))
+}
+
+/// Returns the moves in algebraic notation, separated by spaces.
+fn list_moves(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|p_move| p_move.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,46 @@
+//! A Reversi/Othello engine. The engine finds moves using an iterative
+//! deepening principal variation search over bitboards, which switches
+//! to an exact endgame solver once few squares are empty.
+//!
+//! ```
+//! use othello::{alphabeta_move_gen, OthelloPosition, SearchConfig, TimeManager};
+//! use std::time::Instant;
+//!
+//! let board: OthelloPosition = "BEEEEEEEEEEEEEEEEEEEEEEEEEEEOXEEEEEEXOEEEEEEEEEEEEEEEEEEEEEEEEEEE"
+//!     .parse()
+//!     .unwrap();
+//! let time = TimeManager::new(Instant::now(), 100);
+//! let best_move = alphabeta_move_gen(&board, &time, &SearchConfig::default());
+//! assert!(best_move.is_some());
+//! ```
+
+pub mod alphabeta;
+pub mod board;
+pub mod book;
+pub mod endgame;
+pub mod engine;
+pub mod evaluator;
+pub mod external;
+pub mod gtp;
+pub mod interactive;
+pub mod match_runner;
+pub mod move_generator;
+mod move_ordering;
+pub mod nboard;
+pub mod parity;
+pub mod perft;
+pub mod statistics;
+pub mod symmetry;
+pub mod time_manager;
+mod transposition;
+mod zobrist;
+
+pub use crate::alphabeta::{alphabeta_move_gen, search, search_moves, SearchConfig, SearchResult};
+pub use crate::board::{OthelloPosition, ParsePositionError};
+pub use crate::book::{Book, BookMove};
+pub use crate::endgame::{EndgameSolver, Outcome};
+pub use crate::engine::Engine;
+pub use crate::evaluator::{Evaluate, Evaluator, ParseEvaluatorError};
+pub use crate::move_generator::{get_moves, Move, ParseMoveError};
+pub use crate::perft::{divide, perft};
+pub use crate::time_manager::TimeManager;
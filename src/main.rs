@@ -1,24 +1,524 @@
-mod alphabeta;
-mod board;
-mod evaluator;
-mod move_generator;
-
-use crate::alphabeta::alphabeta_move_gen;
-use crate::board::OthelloPosition;
+use othello::external::{ExternalPlayer, DEFAULT_TIMEOUT_MARGIN_MS};
+use othello::match_runner::{self, EnginePlayer, MatchSummary, Player};
+use othello::move_generator::is_legal;
+use othello::statistics::{self, EloEstimate, Sprt, SprtDecision};
+use othello::{
+    alphabeta_move_gen, divide, gtp, interactive, nboard, Book, EndgameSolver, Engine, Evaluator, Move,
+    OthelloPosition, Outcome, SearchConfig, TimeManager,
+};
 use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
 use std::time;
 
-fn main() {
+const USAGE: &str = "Usage:
+    othello <position> <time limit in seconds> [--threads <count>] [--canonical-table]
+            [--tuple] [--book <file>]
+    othello solve <position> <time limit in seconds> [--wld]
+    othello perft <depth> [<position>]
+    othello play [--white] [--time <seconds per move>] [--threads <count>] [--canonical-table]
+    othello match [--games <count>] [--openings <file> | --opening-plies <count>]
+                  [--transcript <file>] [--threads <count>] [--canonical-table] [--json]
+                  [--sprt <elo0>,<elo1> [--alpha <probability>] [--beta <probability>]]
+                  [--timeout-margin <seconds>]
+                  [--a-time <seconds per move>] [--a-depth <depth>] [--a-eval <weights>]
+                  [--a-book <file>] [--a-engine <executable>]
+                  [--b-time <seconds per move>] [--b-depth <depth>] [--b-eval <weights>]
+                  [--b-book <file>] [--b-engine <executable>]
+    othello book <file> [--plies <count>] [--time <seconds per position>]
+                 [--margin <pieces>] [--threads <count>] [--canonical-table]
+    othello nboard [--time <seconds per move>] [--threads <count>] [--canonical-table]
+                   [--book <file>]
+    othello gtp [--time <seconds per move>] [--threads <count>] [--canonical-table]";
+/// The time the engine spends on each move in interactive play by default.
+const DEFAULT_MOVE_TIME_MS: u64 = 1000;
+/// The longest time limit accepted, in seconds, which is one day.
+const MAX_TIME_LIMIT_SECONDS: f64 = 86_400.0;
+/// The time each engine spends on each move in a match by default.
+const DEFAULT_MATCH_MOVE_TIME_MS: u64 = 100;
+/// The number of games in a match by default.
+const DEFAULT_MATCH_GAMES: usize = 10;
+/// The number of moves played from the starting position to reach the
+/// openings of a match by default.
+const DEFAULT_OPENING_PLIES: u32 = 3;
+/// The file games of a match are written to by default.
+const DEFAULT_TRANSCRIPT: &str = "match.txt";
+/// The probability of a false positive, and of a false negative, of an SPRT by default.
+const DEFAULT_SPRT_ERROR: f64 = 0.05;
+/// The number of moves from the starting position a book is built for by default.
+const DEFAULT_BOOK_PLIES: u32 = 6;
+/// The time spent analysing each position of a book by default.
+const DEFAULT_BOOK_TIME_MS: u64 = 1000;
+/// The greatest difference in pieces to the best move of the moves
+/// played from a book, by default.
+const DEFAULT_BOOK_MARGIN: f64 = 2.0;
 
-    let args: Vec<String> = env::args().collect();
+fn main() {
     let start_time = time::Instant::now();
-    let pos_string = &args[1];
-    let time_limit = (&args[2]).parse::<u64>().unwrap(); 
-    let board = OthelloPosition::new(pos_string);
-    let response = match alphabeta_move_gen(&board, start_time, time_limit) {
-        Some(m) => format!("({},{})", m.row, m.col),
-        None => String::from("pass"),
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("solve") => solve(&args[2..], start_time),
+        Some("perft") => run_perft(&args[2..], start_time),
+        Some("play") => play(&args[2..]),
+        Some("match") => run_match(&args[2..]),
+        Some("nboard") => run_nboard(&args[2..]),
+        Some("gtp") => run_gtp(&args[2..]),
+        Some("book") => build_book(&args[2..]),
+        _ => find_move(&args[1..], start_time),
+    }
+}
+
+/// Prints the move recommended for the given board in algebraic notation,
+/// or with `--tuple` as the one-based `(row,col)`. Prints `pass` if there
+/// is no legal move. With `--book`, a move of the opening book is played
+/// instead of searching whenever the book has one.
+fn find_move(args: &[String], start_time: time::Instant) {
+    let mut args = args.to_vec();
+    let tuple = take_flag(&mut args, "--tuple");
+    let book = take_option(&mut args, "--book").map(|path| load_book(&path));
+    let config = search_config(&mut args);
+    if args.len() != 2 {
+        exit_with_error(USAGE);
+    }
+    let board = parse_position(&args[0]);
+    let time = TimeManager::new(start_time, parse_time_limit(&args[1]));
+    let book_move = book
+        .and_then(|book| book.choose(&board, &mut rand::thread_rng()))
+        .filter(|p_move| is_legal(&board, p_move));
+    let best_move = book_move
+        .or_else(|| alphabeta_move_gen(&board, &time, &config))
+        .unwrap_or_else(|| Move::pass(board.to_move()));
+    let response = if tuple {
+        best_move.tuple_notation()
+    } else {
+        best_move.to_string()
     };
 
     print!("{}", response);
 }
+
+/// Solves the given board to the end of the game and prints the exact
+/// difference in piece count for the player to go next, or with `--wld`
+/// only whether that player wins, draws or loses.
+fn solve(args: &[String], start_time: time::Instant) {
+    let wld = args.len() == 3 && args[2] == "--wld";
+    if args.len() != 2 && !wld {
+        exit_with_error(USAGE);
+    }
+    let board = parse_position(&args[0]);
+    let time = TimeManager::new(start_time, parse_time_limit(&args[1]));
+    let mut solver = EndgameSolver::new(&time);
+    let response = if wld {
+        solver.outcome(&board).map(|outcome| match outcome {
+            Outcome::Win => String::from("win"),
+            Outcome::Draw => String::from("draw"),
+            Outcome::Loss => String::from("loss"),
+        })
+    } else {
+        solver.exact_score(&board).map(|score| format!("{:+}", score))
+    };
+
+    match response {
+        Some(response) => print!("{}", response),
+        None => exit_with_error("Could not solve the position within the time limit"),
+    }
+}
+
+/// Prints the perft count of every legal move in the given board, or in the
+/// starting position if no board is given, followed by their total.
+fn run_perft(args: &[String], start_time: time::Instant) {
+    if args.is_empty() || args.len() > 2 {
+        exit_with_error(USAGE);
+    }
+    let depth = parse_count(&args[0], "depth") as u32;
+    let board = match args.get(1) {
+        Some(position) => parse_position(position),
+        None => OthelloPosition::start(),
+    };
+
+    let mut total = 0;
+    for (p_move, count) in divide(&board, depth) {
+        println!("{} {}", p_move, count);
+        total += count;
+    }
+    println!();
+    println!("total {}", total);
+    eprintln!("{:.3}s", start_time.elapsed().as_secs_f64());
+}
+
+/// Starts a game between the user and the engine in the terminal. The
+/// user plays black unless `--white` is given.
+fn play(args: &[String]) {
+    let mut args = args.to_vec();
+    let human_white = take_flag(&mut args, "--white");
+    let move_time_ms = match take_option(&mut args, "--time") {
+        Some(seconds) => parse_time_limit(&seconds),
+        None => DEFAULT_MOVE_TIME_MS,
+    };
+    let config = search_config(&mut args);
+    if !args.is_empty() {
+        exit_with_error(USAGE);
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = interactive::play(
+        &mut stdin.lock(),
+        &mut stdout.lock(),
+        human_white,
+        move_time_ms,
+        &config,
+    ) {
+        exit_with_error(&format!("Could not play the game: {}", e));
+    }
+}
+
+/// Builds an opening book by analysing the positions reachable from the
+/// starting position, adding to the book in the file if there is one.
+/// Only moves within the margin of the best move are followed and played.
+fn build_book(args: &[String]) {
+    let mut args = args.to_vec();
+    let plies = take_option(&mut args, "--plies")
+        .map_or(DEFAULT_BOOK_PLIES, |plies| parse_count(&plies, "ply count") as u32);
+    let time_ms = take_option(&mut args, "--time")
+        .map_or(DEFAULT_BOOK_TIME_MS, |seconds| parse_time_limit(&seconds));
+    let margin = take_option(&mut args, "--margin").map_or(DEFAULT_BOOK_MARGIN, |margin| {
+        match margin.parse::<f64>() {
+            Ok(parsed) if (0.0..=64.0).contains(&parsed) => parsed,
+            _ => exit_with_error(&format!(
+                "Invalid margin '{}': expected a number of pieces from 0 to 64",
+                margin
+            )),
+        }
+    });
+    let config = search_config(&mut args);
+    if args.len() != 1 {
+        exit_with_error(USAGE);
+    }
+    let path = &args[0];
+
+    let mut book = if Path::new(path).exists() {
+        load_book(path)
+    } else {
+        Book::new()
+    };
+    let mut engine = Engine::new(config);
+    let analysed = book.build(
+        &mut engine,
+        &OthelloPosition::start(),
+        plies,
+        time_ms,
+        (margin * 100.0).round() as i16,
+    );
+    if let Err(e) = book.save(path) {
+        exit_with_error(&format!("Could not write '{}': {}", path, e));
+    }
+    println!(
+        "Analysed {} new positions, the book holds {} positions",
+        analysed,
+        book.len()
+    );
+}
+
+/// Speaks the NBoard protocol on standard input and output, for use
+/// as the engine of an Othello GUI. With `--book`, finished games are
+/// learned into the opening book in the file.
+fn run_nboard(args: &[String]) {
+    let mut args = args.to_vec();
+    let book_path = take_option(&mut args, "--book").map(PathBuf::from);
+    let (config, move_time_ms) = protocol_settings(&args);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = nboard::run(
+        &mut stdin.lock(),
+        &mut stdout.lock(),
+        config,
+        move_time_ms,
+        book_path.as_deref(),
+    ) {
+        exit_with_error(&format!("Could not talk to the GUI: {}", e));
+    }
+}
+
+/// Answers commands of the GTP-like protocol on standard input and output.
+fn run_gtp(args: &[String]) {
+    let (config, move_time_ms) = protocol_settings(args);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = gtp::run(&mut stdin.lock(), &mut stdout.lock(), config, move_time_ms) {
+        exit_with_error(&format!("Could not answer commands: {}", e));
+    }
+}
+
+/// Returns the settings of the searches and the time per move given to
+/// a protocol front end.
+fn protocol_settings(args: &[String]) -> (SearchConfig, u64) {
+    let mut args = args.to_vec();
+    let move_time_ms = match take_option(&mut args, "--time") {
+        Some(seconds) => parse_time_limit(&seconds),
+        None => DEFAULT_MOVE_TIME_MS,
+    };
+    let config = search_config(&mut args);
+    if !args.is_empty() {
+        exit_with_error(USAGE);
+    }
+
+    (config, move_time_ms)
+}
+
+/// Plays a match between two engines, A and B, which can differ in their
+/// time per move, depth limit and evaluation weights. Writes the games to
+/// the transcript and prints the results of A along with its Elo difference
+/// to B. With `--sprt`, the match stops once the test reaches a decision.
+/// With `--json`, the results are printed as a single JSON object.
+fn run_match(args: &[String]) {
+    let mut args = args.to_vec();
+    let json = take_flag(&mut args, "--json");
+    let sprt = take_option(&mut args, "--sprt").map(|bounds| {
+        let (elo0, elo1) = match bounds.split_once(',') {
+            Some((elo0, elo1)) => (elo0.trim().parse(), elo1.trim().parse()),
+            None => exit_with_error(&format!("Invalid SPRT bounds '{}'", bounds)),
+        };
+        let (elo0, elo1) = match (elo0, elo1) {
+            (Ok(elo0), Ok(elo1)) if elo0 < elo1 && f64::is_finite(elo0) && f64::is_finite(elo1) => {
+                (elo0, elo1)
+            }
+            _ => exit_with_error(&format!(
+                "Invalid SPRT bounds '{}': expected two finite Elo differences, the lower first",
+                bounds
+            )),
+        };
+        let alpha = take_option(&mut args, "--alpha")
+            .map_or(DEFAULT_SPRT_ERROR, |alpha| parse_probability(&alpha, "alpha"));
+        let beta = take_option(&mut args, "--beta")
+            .map_or(DEFAULT_SPRT_ERROR, |beta| parse_probability(&beta, "beta"));
+        Sprt::new(elo0, elo1, alpha, beta)
+    });
+    let games = take_option(&mut args, "--games")
+        .map_or(DEFAULT_MATCH_GAMES, |games| parse_count(&games, "game count"));
+    let openings = match take_option(&mut args, "--openings") {
+        Some(path) => read_openings(&path),
+        None => {
+            let plies = take_option(&mut args, "--opening-plies").map_or(
+                DEFAULT_OPENING_PLIES,
+                |plies| parse_count(&plies, "opening ply count") as u32,
+            );
+            match_runner::openings_after(plies)
+        }
+    };
+    let transcript_path =
+        take_option(&mut args, "--transcript").unwrap_or_else(|| String::from(DEFAULT_TRANSCRIPT));
+    let config = search_config(&mut args);
+    let timeout_margin_ms = take_option(&mut args, "--timeout-margin")
+        .map_or(DEFAULT_TIMEOUT_MARGIN_MS, |seconds| parse_time_limit(&seconds));
+    let mut engine_a = engine_player(&mut args, "a", &config, timeout_margin_ms);
+    let mut engine_b = engine_player(&mut args, "b", &config, timeout_margin_ms);
+    if !args.is_empty() || openings.is_empty() {
+        exit_with_error(USAGE);
+    }
+
+    let mut transcript = match File::create(&transcript_path) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => exit_with_error(&format!("Could not create '{}': {}", transcript_path, e)),
+    };
+    let summary = match match_runner::play_match(
+        engine_a.as_mut(),
+        engine_b.as_mut(),
+        &openings,
+        games,
+        sprt.as_ref(),
+        &mut transcript,
+    ) {
+        Ok(summary) => summary,
+        Err(e) => exit_with_error(&format!("Could not write '{}': {}", transcript_path, e)),
+    };
+    if json {
+        println!("{}", statistics::match_json(&summary, sprt.as_ref()));
+    } else {
+        print_match_summary(&summary, sprt.as_ref());
+    }
+}
+
+/// Prints the results of a match for a human reader.
+fn print_match_summary(summary: &MatchSummary, sprt: Option<&Sprt>) {
+    let estimate = EloEstimate::from_summary(summary);
+    println!(
+        "A against B: {} wins, {} draws, {} losses in {} games ({:.1}%)",
+        summary.wins,
+        summary.draws,
+        summary.losses,
+        summary.games(),
+        100.0 * summary.score()
+    );
+    println!(
+        "Elo difference: {:+.1} (95% confidence interval {:+.1} to {:+.1})",
+        estimate.elo, estimate.lower, estimate.upper
+    );
+    if let Some(sprt) = sprt {
+        let decision = match sprt.decide(summary) {
+            SprtDecision::AcceptH1 => "H1 accepted, A is stronger",
+            SprtDecision::AcceptH0 => "H0 accepted, A is not stronger",
+            SprtDecision::Continue => "no decision yet",
+        };
+        println!(
+            "SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}), {}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.llr(summary),
+            sprt.lower_bound(),
+            sprt.upper_bound(),
+            decision
+        );
+    }
+}
+
+/// Returns the engine of a match taking the options starting with the
+/// given prefix, such as `--a-time`, on top of the given settings. With
+/// `--a-engine`, the engine is an external executable, followed by any
+/// arguments it takes before the position, and the search options are ignored.
+fn engine_player(
+    args: &mut Vec<String>,
+    prefix: &str,
+    config: &SearchConfig,
+    timeout_margin_ms: u64,
+) -> Box<dyn Player> {
+    let mut config = config.clone();
+    let name = prefix.to_uppercase();
+    let move_time_ms = take_option(args, &format!("--{}-time", prefix))
+        .map_or(DEFAULT_MATCH_MOVE_TIME_MS, |seconds| parse_time_limit(&seconds));
+    if let Some(command) = take_option(args, &format!("--{}-engine", prefix)) {
+        let mut words = command.split_whitespace().map(String::from);
+        let program = match words.next() {
+            Some(program) => program,
+            None => exit_with_error(&format!("Missing executable for --{}-engine", prefix)),
+        };
+        let player = ExternalPlayer::new(&program, words.collect(), move_time_ms, timeout_margin_ms);
+        let name = format!("{} ({})", name, player.name());
+        return Box::new(player.with_name(&name));
+    }
+    if let Some(depth) = take_option(args, &format!("--{}-depth", prefix)) {
+        config.max_depth = parse_count(&depth, "depth") as u32;
+    }
+    if let Some(weights) = take_option(args, &format!("--{}-eval", prefix)) {
+        match weights.parse::<Evaluator>() {
+            Ok(evaluator) => config.evaluator = Arc::new(evaluator),
+            Err(e) => exit_with_error(&format!("Invalid weights '{}': {}", weights, e)),
+        }
+    }
+    let player = EnginePlayer::new(&name, config, move_time_ms);
+
+    match take_option(args, &format!("--{}-book", prefix)) {
+        Some(path) => Box::new(player.with_book(Arc::new(load_book(&path)))),
+        None => Box::new(player),
+    }
+}
+
+/// Reads the openings of a match from a file holding one position per
+/// line. Empty lines and lines starting with `#` are skipped.
+fn read_openings(path: &str) -> Vec<OthelloPosition> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(parse_position)
+            .collect(),
+        Err(e) => exit_with_error(&format!("Could not read '{}': {}", path, e)),
+    }
+}
+
+/// Returns the settings of the searches, taking the options shared by
+/// all commands searching: `--threads` and `--canonical-table`.
+fn search_config(args: &mut Vec<String>) -> SearchConfig {
+    let mut config = SearchConfig::default();
+    if let Some(threads) = take_option(args, "--threads") {
+        config.threads = parse_count(&threads, "thread count");
+    }
+    config.canonical_table = take_flag(args, "--canonical-table");
+
+    config
+}
+
+/// Reads the opening book in the given file, exiting with a description
+/// of the problem if it cannot be read.
+fn load_book(path: &str) -> Book {
+    match Book::load(path) {
+        Ok(book) => book,
+        Err(e) => exit_with_error(&format!("Could not read book '{}': {}", path, e)),
+    }
+}
+
+/// Parses a position string, exiting with a description of the problem if it is invalid.
+fn parse_position(position: &str) -> OthelloPosition {
+    match position.parse::<OthelloPosition>() {
+        Ok(board) => board,
+        Err(e) => exit_with_error(&format!("Invalid position '{}': {}", position, e)),
+    }
+}
+
+/// Parses a time limit given in seconds, possibly with a fractional part,
+/// and returns it in milliseconds. Exits with a description of the problem
+/// if it is invalid.
+fn parse_time_limit(seconds: &str) -> u64 {
+    match seconds.parse::<f64>() {
+        Ok(parsed) if (0.0..=MAX_TIME_LIMIT_SECONDS).contains(&parsed) => (parsed * 1000.0) as u64,
+        Ok(_) => exit_with_error(&format!(
+            "Invalid time limit '{}': expected a number of seconds from 0 to {}",
+            seconds, MAX_TIME_LIMIT_SECONDS
+        )),
+        Err(e) => exit_with_error(&format!("Invalid time limit '{}': {}", seconds, e)),
+    }
+}
+
+/// Parses a probability strictly between 0 and 1, exiting with a
+/// description of the problem if it is invalid.
+fn parse_probability(probability: &str, name: &str) -> f64 {
+    match probability.parse::<f64>() {
+        Ok(parsed) if parsed > 0.0 && parsed < 1.0 => parsed,
+        _ => exit_with_error(&format!(
+            "Invalid {} '{}': expected a probability between 0 and 1",
+            name, probability
+        )),
+    }
+}
+
+/// Parses a positive integer, exiting with a description of the problem if it is invalid.
+fn parse_count(count: &str, name: &str) -> usize {
+    match count.parse::<usize>() {
+        Ok(parsed) if parsed > 0 => parsed,
+        _ => exit_with_error(&format!(
+            "Invalid {} '{}': expected a positive integer",
+            name, count
+        )),
+    }
+}
+
+/// Removes a flag from the arguments, returning whether it was given.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes an option and its value from the arguments, returning
+/// the value if the option was given.
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == name)?;
+    if index + 1 >= args.len() {
+        exit_with_error(&format!("Missing value for {}", name));
+    }
+    args.remove(index);
+    Some(args.remove(index))
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,325 @@
+//! This file contains the match runner, which plays games between two
+//! players to find out which one is stronger. Every opening position is
+//! played twice with the players swapping colours, so that neither player
+//! benefits from a lopsided opening. Each game is written to a transcript
+//! as it finishes, and the results of the match are written after the games.
+//!
+//! The players are anything implementing the Player trait. The runner
+//! makes the passes itself, and a player failing to answer or answering
+//! with an illegal move forfeits the game.
+
+use crate::alphabeta::{search, SearchConfig};
+use crate::board::OthelloPosition;
+use crate::book::Book;
+use crate::endgame::Outcome;
+use crate::move_generator::{get_moves, is_legal, Move};
+use crate::statistics::{Sprt, SprtDecision};
+use crate::symmetry::canonical;
+use crate::time_manager::TimeManager;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A participant in a match.
+pub trait Player {
+    /// Returns the name of the player, as written in the transcript.
+    fn name(&self) -> &str;
+
+    /// Returns the move the player makes in the board, which has at least
+    /// one legal move. An error forfeits the game.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board the player is to move in.
+    fn choose_move(&mut self, board: &OthelloPosition) -> io::Result<Move>;
+}
+
+/// A player searching with the engine itself, optionally playing the
+/// first moves of its games from an opening book.
+pub struct EnginePlayer {
+    name: String,
+    config: SearchConfig,
+    move_time_ms: u64,
+    book: Option<Arc<Book>>,
+}
+
+impl EnginePlayer {
+    /// Returns a new EnginePlayer.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the player.
+    /// * `config` - The settings of the searches of the player.
+    /// * `move_time_ms` - The time in milliseconds the player may spend on each move.
+    pub fn new(name: &str, config: SearchConfig, move_time_ms: u64) -> EnginePlayer {
+        EnginePlayer {
+            name: name.to_string(),
+            config,
+            move_time_ms,
+            book: None,
+        }
+    }
+
+    /// Returns the player playing the moves of the given opening book
+    /// whenever the book has one, instead of searching.
+    pub fn with_book(self, book: Arc<Book>) -> EnginePlayer {
+        EnginePlayer {
+            book: Some(book),
+            ..self
+        }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn choose_move(&mut self, board: &OthelloPosition) -> io::Result<Move> {
+        let book_move = self
+            .book
+            .as_ref()
+            .and_then(|book| book.choose(board, &mut rand::thread_rng()));
+        if let Some(p_move) = book_move.filter(|p_move| is_legal(board, p_move)) {
+            return Ok(p_move);
+        }
+        let time = TimeManager::new(Instant::now(), self.move_time_ms);
+        search(board, &time, &self.config)
+            .best_move
+            .ok_or_else(|| io::Error::other("the search found no move"))
+    }
+}
+
+/// A finished game.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// The name of the player with the black pieces.
+    pub black: String,
+    /// The name of the player with the white pieces.
+    pub white: String,
+    /// The board the game started from.
+    pub opening: OthelloPosition,
+    /// The moves played from the opening, including passes.
+    pub moves: Vec<Move>,
+    /// The board the game ended on.
+    pub final_board: OthelloPosition,
+    /// Whether white forfeited the game, along with the reason,
+    /// if the game was decided by a forfeit.
+    pub forfeit: Option<(bool, String)>,
+}
+
+impl GameRecord {
+    /// Returns the result of the game for the player with the black pieces.
+    pub fn black_outcome(&self) -> Outcome {
+        match &self.forfeit {
+            Some((true, _)) => Outcome::Win,
+            Some((false, _)) => Outcome::Loss,
+            None => match self.final_board.disc_difference() {
+                0 => Outcome::Draw,
+                difference if difference < 0 => Outcome::Win,
+                _ => Outcome::Loss,
+            },
+        }
+    }
+
+    /// Writes the game to the transcript as a block of tagged lines,
+    /// followed by an empty line.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The transcript.
+    /// * `number` - The number of the game in the match, starting from 1.
+    pub fn write<W: Write>(&self, output: &mut W, number: usize) -> io::Result<()> {
+        let moves: Vec<String> = self.moves.iter().map(Move::to_string).collect();
+        let result = match &self.forfeit {
+            Some((white, reason)) => format!(
+                "{} forfeits: {}",
+                if *white { "White" } else { "Black" },
+                reason
+            ),
+            None => format!(
+                "{}-{}",
+                self.final_board.black().count_ones(),
+                self.final_board.white().count_ones()
+            ),
+        };
+        writeln!(output, "[Game {}]", number)?;
+        writeln!(output, "[Black {}]", self.black)?;
+        writeln!(output, "[White {}]", self.white)?;
+        writeln!(output, "[Opening {}]", self.opening)?;
+        writeln!(output, "[Moves {}]", moves.join(" "))?;
+        writeln!(output, "[Result {}]", result)?;
+        writeln!(output)
+    }
+}
+
+/// The results of a match from the perspective of the first player.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MatchSummary {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchSummary {
+    /// Returns the number of games played.
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the share of the points won by the first player, counting
+    /// a draw as half a win, or 0.5 if no games were played.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    /// Writes the results to the transcript as a block of tagged lines,
+    /// followed by an empty line.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The transcript.
+    /// * `first` - The name of the player the results are from the perspective of.
+    /// * `second` - The name of the opponent of the first player.
+    pub fn write<W: Write>(&self, output: &mut W, first: &str, second: &str) -> io::Result<()> {
+        writeln!(output, "[Match {} against {}]", first, second)?;
+        writeln!(output, "[Games {}]", self.games())?;
+        writeln!(
+            output,
+            "[Score {} wins, {} draws, {} losses]",
+            self.wins, self.draws, self.losses
+        )?;
+        writeln!(output)
+    }
+}
+
+/// Plays a single game from the opening between the given players.
+///
+/// # Arguments
+///
+/// * `black` - The player with the black pieces.
+/// * `white` - The player with the white pieces.
+/// * `opening` - The board the game starts from.
+pub fn play_game(
+    black: &mut dyn Player,
+    white: &mut dyn Player,
+    opening: &OthelloPosition,
+) -> GameRecord {
+    let mut board = opening.clone();
+    let mut moves = Vec::new();
+    let mut forfeit = None;
+    while !board.is_game_over() {
+        let legal_moves = get_moves(&board);
+        let p_move = if legal_moves.is_empty() {
+            Move::pass(board.to_move())
+        } else {
+            let choice = if board.max_player() {
+                white.choose_move(&board)
+            } else {
+                black.choose_move(&board)
+            };
+            match choice {
+                Ok(p_move) if legal_moves.contains(&p_move) => p_move,
+                Ok(p_move) => {
+                    forfeit = Some((board.max_player(), format!("illegal move {}", p_move)));
+                    break;
+                }
+                Err(e) => {
+                    forfeit = Some((board.max_player(), e.to_string()));
+                    break;
+                }
+            }
+        };
+        moves.push(p_move);
+        board = Move::make_move(&board, &p_move);
+    }
+
+    GameRecord {
+        black: black.name().to_string(),
+        white: white.name().to_string(),
+        opening: opening.clone(),
+        moves,
+        final_board: board,
+        forfeit,
+    }
+}
+
+/// Plays a match of the given number of games between two players and
+/// writes every game to the transcript. The games cycle through the
+/// openings, playing each one twice so that both players get to play
+/// both colours, starting with the first player as black. With an SPRT,
+/// the match stops early once the test reaches a decision, which is only
+/// checked after both games of an opening. The results are written to the
+/// transcript after the last game.
+///
+/// # Arguments
+///
+/// * `first` - The player the summary is from the perspective of.
+/// * `second` - The opponent of the first player.
+/// * `openings` - The boards the games start from, which must not be empty.
+/// * `games` - The greatest number of games to play.
+/// * `sprt` - The test deciding whether the first player is stronger, if any.
+/// * `transcript` - Where the games and the results are written.
+pub fn play_match<W: Write>(
+    first: &mut dyn Player,
+    second: &mut dyn Player,
+    openings: &[OthelloPosition],
+    games: usize,
+    sprt: Option<&Sprt>,
+    transcript: &mut W,
+) -> io::Result<MatchSummary> {
+    let mut summary = MatchSummary::default();
+    for game in 0..games {
+        let opening = &openings[(game / 2) % openings.len()];
+        let first_is_black = game % 2 == 0;
+        let record = if first_is_black {
+            play_game(first, second, opening)
+        } else {
+            play_game(second, first, opening)
+        };
+        record.write(transcript, game + 1)?;
+        transcript.flush()?;
+
+        match (record.black_outcome(), first_is_black) {
+            (Outcome::Draw, _) => summary.draws += 1,
+            (Outcome::Win, true) | (Outcome::Loss, false) => summary.wins += 1,
+            _ => summary.losses += 1,
+        }
+        if !first_is_black
+            && sprt.is_some_and(|sprt| sprt.decide(&summary) != SprtDecision::Continue)
+        {
+            break;
+        }
+    }
+    summary.write(transcript, first.name(), second.name())?;
+    transcript.flush()?;
+
+    Ok(summary)
+}
+
+/// Returns every distinct board reached after playing the given number of
+/// moves from the starting position, in the order they are first reached.
+/// Boards which are rotations or reflections of an earlier board are left
+/// out, since the game is the same from them.
+///
+/// # Arguments
+///
+/// * `plies` - The number of moves played from the starting position.
+pub fn openings_after(plies: u32) -> Vec<OthelloPosition> {
+    let mut openings = vec![OthelloPosition::start()];
+    for _ in 0..plies {
+        let mut seen = HashSet::new();
+        openings = openings
+            .iter()
+            .flat_map(OthelloPosition::generate_children)
+            .map(|(_, child)| child)
+            .filter(|child| seen.insert(canonical(child).0.hash()))
+            .collect();
+    }
+
+    openings
+}
@@ -1,15 +1,39 @@
-use crate::board::{OthelloPosition, BOARD_SIZE, EMPTY_CELL, PLAYER_BLACK, PLAYER_WHITE};
-
-#[derive(Debug, Clone, PartialEq)]
-/// This file contains the implementation logic of a Move.
-/// Additionally, it contains a rewrite of the move generation code
-/// in the file OthelloPosition.java, given in the assignment.
-/// It should therefore be noted that I should probably not be credited
-/// as the author of the implementation of the logic in get_moves and the 
-/// functions it relies on, although these have been slightly improved
-/// from the given versions.
+//! This file contains the implementation logic of a Move.
+//! Additionally, it contains the move generation code. It started out as
+//! a rewrite of the code in the file OthelloPosition.java, given in the
+//! assignment, but now works on bitboards: the legal moves and the pieces
+//! captured by a move are computed by shifting whole lines of pieces at once
+//! instead of walking the board square by square.
+
+use crate::board::{OthelloPosition, BOARD_SIZE};
+use std::error::Error;
+use std::fmt;
+
+/// All squares except those in the leftmost column.
+const NOT_FIRST_COL: u64 = 0xfefe_fefe_fefe_fefe;
+/// All squares except those in the rightmost column.
+const NOT_LAST_COL: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+/// The eight directions a line of captured pieces can extend in, given as
+/// the shift moving a bit one step in that direction along with the mask
+/// removing bits which wrapped around to the other side of the board.
+/// Positive shifts move towards higher rows or columns.
+const DIRECTIONS: [(isize, u64); 8] = [
+    (-8, u64::MAX), // North
+    (-7, NOT_FIRST_COL), // North east
+    (1, NOT_FIRST_COL), // East
+    (9, NOT_FIRST_COL), // South east
+    (8, u64::MAX), // South
+    (7, NOT_LAST_COL), // South west
+    (-1, NOT_LAST_COL), // West
+    (-9, NOT_LAST_COL), // North west
+];
+
+/// The notation of a pass, both in algebraic and tuple form.
+const PASS_NOTATION: &str = "pass";
 
 /// A representation of a Move.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Move {
     /// A char representing the colour of the player to add a piece.
     player: char,
@@ -21,9 +45,9 @@ pub struct Move {
 
 impl Move {
     /// Returns a new instance of Move with the given player, row, and column.
-    /// 
+    ///
     /// # Arguments
-    /// 
+    ///
     /// * `player` - a char representation of the colour of the player to add a piece.
     /// * `row` - the row where the piece should be added.
     /// * `col` - the column where the piece should be added.
@@ -31,269 +55,287 @@ impl Move {
         Move { player, row, col }
     }
 
-    /// Takes a board and a move, adds the piece to the given board in the specified
-    /// location and returns the resulting board as an OthelloPosition.
-    /// 
+    /// Returns a Move passing the turn to the opponent, which is only legal
+    /// when the player has no other move. A pass is placed outside the board.
+    ///
     /// # Arguments
-    /// 
-    /// * `board` - The board to add a piece to.
-    /// * `to_make` - A Move to be carried out on the given board.
-    pub fn make_move<'a>(board: &OthelloPosition, to_make: &Move) -> OthelloPosition {
-        board.add_piece(to_make.row + 1, to_make.col + 1, to_make.player)
+    ///
+    /// * `player` - a char representation of the colour of the player passing.
+    pub fn pass(player: char) -> Move {
+        Move::new(player, BOARD_SIZE, BOARD_SIZE)
     }
-}
 
-/// This function and the function it utilizies are heavily based on their
-/// Java-based implementation given in the assignment files.
-pub fn get_moves(board: &OthelloPosition) -> std::vec::Vec<Move> {
-    let mut val_moves = Vec::new();
-    let mut candidates = [[false; BOARD_SIZE + 2]; BOARD_SIZE + 2];
-    for i in 0..BOARD_SIZE {
-        for j in 0..BOARD_SIZE {
-            candidates[i][j] = is_candidate(board, i + 1, j + 1);
-        }
-    }
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if candidates[row][col] {
-                if is_move(board, row + 1, col + 1) {
-                    match board.max_player {
-                        true => val_moves.push(Move::new(PLAYER_WHITE, row, col)),
-                        false => val_moves.push(Move::new(PLAYER_BLACK, row, col)),
-                    }
-                }
-            }
-        }
+    /// Returns whether the move is a pass.
+    pub fn is_pass(&self) -> bool {
+        self.row >= BOARD_SIZE || self.col >= BOARD_SIZE
     }
 
-    val_moves
-}
-
-fn is_move(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    check_north(board, row, col)
-        || check_north_east(board, row, col)
-        || check_east(board, row, col)
-        || check_south_east(board, row, col)
-        || check_south(board, row, col)
-        || check_south_west(board, row, col)
-        || check_north_west(board, row, col)
-        || check_west(board, row, col)
-}
-
-fn check_north(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row - 1, col) {
-        return false;
-    }
-    let mut i: isize = row as isize - 2;
-    while i > 0 {
-        if is_free(board, i as usize, col) {
-            return false;
+    /// Parses a move in algebraic notation, a column a-h followed by a
+    /// row 1-8 such as `d3`, or `pass`. Letters may be upper or lower case.
+    ///
+    /// # Arguments
+    ///
+    /// * `notation` - The move to parse.
+    /// * `player` - a char representation of the colour of the player making the move.
+    pub fn parse(notation: &str, player: char) -> Result<Move, ParseMoveError> {
+        let notation = notation.trim();
+        if notation.eq_ignore_ascii_case(PASS_NOTATION) {
+            return Ok(Move::pass(player));
         }
-        if is_own_square(board, i as usize, col) {
-            return true;
+        let mut chars = notation.chars();
+        let (col, row) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(col), Some(row), None) => (col.to_ascii_lowercase(), row),
+            _ => return Err(ParseMoveError::BadFormat(notation.to_string())),
+        };
+        if !('a'..='h').contains(&col) {
+            return Err(ParseMoveError::InvalidColumn(col));
+        }
+        if !('1'..='8').contains(&row) {
+            return Err(ParseMoveError::InvalidRow(row));
         }
-        i -= 1;
-    }
-    false
-}
 
-fn check_east(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row, col + 1) {
-        return false;
+        Ok(Move::new(
+            player,
+            row as usize - '1' as usize,
+            col as usize - 'a' as usize,
+        ))
     }
 
-    let mut i = col + 2;
-    while i <= BOARD_SIZE {
-        if is_free(board, row, i) {
-            return false;
+    /// Returns the move in the one-based `(row,col)` notation used before
+    /// algebraic notation, or `pass`.
+    pub fn tuple_notation(&self) -> String {
+        if self.is_pass() {
+            String::from(PASS_NOTATION)
+        } else {
+            format!("({},{})", self.row + 1, self.col + 1)
         }
-        if is_own_square(board, row, i) {
-            return true;
-        }
-        i += 1;
     }
 
-    false
-}
-
-fn check_south(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row + 1, col) {
-        return false;
+    /// Returns the char representation of the colour of the player making the move.
+    pub fn player(&self) -> char {
+        self.player
     }
 
-    let mut i = row + 2;
-    while i <= BOARD_SIZE {
-        if is_free(board, i, col) {
-            return false;
-        }
-        if is_own_square(board, i, col) {
-            return true;
+    /// Takes a board and a move, adds the piece to the given board in the specified
+    /// location, flips all pieces captured by it and returns the resulting board
+    /// as an OthelloPosition.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board to add a piece to.
+    /// * `to_make` - A Move to be carried out on the given board.
+    pub fn make_move(board: &OthelloPosition, to_make: &Move) -> OthelloPosition {
+        if to_make.is_pass() {
+            board.pass()
+        } else {
+            board.play(to_make.row, to_make.col)
         }
-
-        i += 1;
     }
-
-    false
 }
 
-fn check_west(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row, col - 1) {
-        return false;
-    }
-
-    let mut i: isize = col as isize - 2;
-    while i > 0 {
-        if is_free(board, row, i as usize) {
-            return false;
+/// Writes the move in algebraic notation, such as `d3`, or `pass`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_pass() {
+            write!(f, "{}", PASS_NOTATION)
+        } else {
+            write!(
+                f,
+                "{}{}",
+                (b'a' + self.col as u8) as char,
+                self.row + 1
+            )
         }
-        if is_own_square(board, row, i as usize) {
-            return true;
-        }
-        i -= 1;
     }
+}
 
-    false
+/// The reasons a move in algebraic notation can fail to parse.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseMoveError {
+    /// The move is neither `pass` nor two chars long. Holds the move.
+    BadFormat(String),
+    /// The column is not a letter between 'a' and 'h'. Holds the char found.
+    InvalidColumn(char),
+    /// The row is not a digit between '1' and '8'. Holds the char found.
+    InvalidRow(char),
 }
 
-fn check_north_east(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row - 1, col + 1) {
-        return false;
-    }
-    let mut i: isize = 2;
-    while (row as isize - i) as isize > 0 && (col + i as usize) <= BOARD_SIZE {
-        if is_free(board, row - i as usize, col + i as usize) {
-            return false;
-        }
-        if is_own_square(board, row - i as usize, col + i as usize) {
-            return true;
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseMoveError::BadFormat(notation) => write!(
+                f,
+                "expected a square such as 'd3' or '{}' but found '{}'",
+                PASS_NOTATION, notation
+            ),
+            ParseMoveError::InvalidColumn(c) => {
+                write!(f, "expected a column between 'a' and 'h' but found '{}'", c)
+            }
+            ParseMoveError::InvalidRow(c) => {
+                write!(f, "expected a row between '1' and '8' but found '{}'", c)
+            }
         }
+    }
+}
 
-        i += 1;
+impl Error for ParseMoveError {}
+
+/// Returns the legal moves of the player to go next on the given board,
+/// ordered by row and then by column.
+pub fn get_moves(board: &OthelloPosition) -> std::vec::Vec<Move> {
+    let player = board.to_move();
+    let mut moves = move_mask(board.player(), board.opponent());
+    let mut val_moves = Vec::with_capacity(moves.count_ones() as usize);
+    while moves != 0 {
+        let square = moves.trailing_zeros() as usize;
+        val_moves.push(Move::new(player, square / BOARD_SIZE, square % BOARD_SIZE));
+        moves &= moves - 1;
     }
 
-    false
+    val_moves
 }
 
-fn check_south_east(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row + 1, col + 1) {
-        return false;
-    }
-    let mut i = 2;
-    while (row + i) <= BOARD_SIZE && (col + i) <= BOARD_SIZE {
-        if is_free(board, row + i, col + i) {
-            return false;
-        }
-        if is_own_square(board, row + i, col + i) {
-            return true;
-        }
-        i += 1;
+/// Returns whether the move can be made by the player to go next in the
+/// board. Passing is only legal without any other legal move, while the
+/// opponent can still move.
+pub fn is_legal(board: &OthelloPosition, p_move: &Move) -> bool {
+    if p_move.is_pass() {
+        !has_moves(board) && !board.is_game_over()
+    } else {
+        move_mask(board.player(), board.opponent()) & (1 << (p_move.row * BOARD_SIZE + p_move.col)) != 0
     }
+}
 
-    false
+/// Returns whether the player to go next has at least one legal move.
+pub fn has_moves(board: &OthelloPosition) -> bool {
+    move_mask(board.player(), board.opponent()) != 0
 }
 
-fn check_south_west(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row + 1, col - 1) {
-        return false;
-    }
-    let mut i: isize = 2;
-    while (row + i as usize) <= BOARD_SIZE && (col as isize - i) > 0 {
-        if is_free(board, row + i as usize, col - i as usize) {
-            return false;
+/// Returns a bitboard of the empty squares where `player` can place a piece
+/// capturing at least one of the pieces of `opponent`.
+///
+/// # Arguments
+///
+/// * `player` - The pieces of the player to move.
+/// * `opponent` - The pieces of the opponent.
+pub fn move_mask(player: u64, opponent: u64) -> u64 {
+    let empty = !(player | opponent);
+    let mut moves = 0;
+    for (shift, mask) in &DIRECTIONS {
+        // Grow the run of opponent pieces adjacent to the player's pieces.
+        // A line can contain at most six capturable pieces.
+        let capturable = opponent & mask;
+        let mut run = capturable & shift_by(player, *shift);
+        for _ in 0..5 {
+            run |= capturable & shift_by(run, *shift);
         }
-        if is_own_square(board, row + i as usize, col - i as usize) {
-            return true;
-        }
-        i += 1;
+        moves |= mask & shift_by(run, *shift);
     }
 
-    false
+    moves & empty
 }
 
-fn check_north_west(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_opponent_square(board, row - 1, col - 1) {
-        return false;
-    }
-    let mut i: isize = 2;
-    while (row as isize - i) > 0 && (col as isize - i) > 0 {
-        if is_free(board, row - i as usize, col - i as usize) {
-            return false;
+/// Returns a bitboard of the pieces of `opponent` which are flipped when
+/// `player` places a piece on the given square.
+///
+/// # Arguments
+///
+/// * `player` - The pieces of the player to move.
+/// * `opponent` - The pieces of the opponent.
+/// * `square` - The index of the square the piece is placed on, `row * 8 + col`.
+pub fn flips(player: u64, opponent: u64, square: usize) -> u64 {
+    let mut flipped = 0;
+    for (shift, mask) in &DIRECTIONS {
+        let mut line = 0;
+        let mut cursor = mask & shift_by(1 << square, *shift);
+        while cursor & opponent != 0 {
+            line |= cursor;
+            cursor = mask & shift_by(cursor, *shift);
         }
-        if is_own_square(board, row - i as usize, col - i as usize) {
-            return true;
+        if cursor & player != 0 {
+            flipped |= line;
         }
-        i += 1;
     }
 
-    false
+    flipped
 }
 
-fn is_opponent_square(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if board.max_player && board.board[row][col] == PLAYER_BLACK {
-        return true;
-    }
-    if (!board.max_player) && board.board[row][col] == PLAYER_WHITE {
-        return true;
-    }
-
-    false
+/// Returns, for each of the eight directions, a bitboard of the squares one
+/// step in that direction from the squares of the given bitboard.
+///
+/// # Arguments
+///
+/// * `bits` - The squares to step from.
+pub fn neighbours(bits: u64) -> [u64; 8] {
+    DIRECTIONS.map(|(shift, mask)| mask & shift_by(bits, shift))
 }
 
-fn is_own_square(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if (!board.max_player) && board.board[row][col] == PLAYER_BLACK {
-        return true;
-    }
-    if board.max_player && board.board[row][col] == PLAYER_WHITE {
-        return true;
-    }
-
-    false
+/// Returns a bitboard of the squares one step in the given direction from
+/// the squares of the given bitboard. The directions are numbered 0 to 7
+/// clockwise starting from north, so direction `d + 4` is opposite `d`.
+///
+/// # Arguments
+///
+/// * `bits` - The squares to step from.
+/// * `direction` - The number of the direction, from 0 to 7.
+pub fn step(bits: u64, direction: usize) -> u64 {
+    let (shift, mask) = DIRECTIONS[direction];
+    mask & shift_by(bits, shift)
 }
 
-fn is_candidate(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    if !is_free(board, row, col) {
-        return false;
-    }
-    if has_neighbor(board, row, col) {
-        return true;
+/// Shifts the bits of the bitboard left for positive shifts and right for
+/// negative shifts.
+fn shift_by(bits: u64, shift: isize) -> u64 {
+    if shift > 0 {
+        bits << shift
+    } else {
+        bits >> -shift
     }
-
-    false
 }
 
-fn has_neighbor(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    !is_free(board, row - 1, col)
-        || !is_free(board, row - 1, col + 1)
-        || !is_free(board, row, col + 1)
-        || !is_free(board, row + 1, col + 1)
-        || !is_free(board, row + 1, col)
-        || !is_free(board, row + 1, col - 1)
-        || !is_free(board, row, col - 1)
-        || !is_free(board, row - 1, col - 1)
-}
-
-fn is_free(board: &OthelloPosition, row: usize, col: usize) -> bool {
-    board.board[row][col] == EMPTY_CELL
-}
-
-
-pub fn get_move_from_board_diff(from: &OthelloPosition, to: &OthelloPosition) -> Option<Move> {
-    for row in 0..from.board.len() {
-        let (differs, col, to_value) = vec_differs(&from.board[row], &to.board[row]);
-        if differs {
-            return Some(Move::new(to_value, row, col as usize));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{PLAYER_BLACK, PLAYER_WHITE};
+
+    #[test]
+    fn every_square_reads_back_the_same() {
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                let p_move = Move::new(PLAYER_BLACK, row, col);
+                let notation = p_move.to_string();
+                assert_eq!(Move::parse(&notation, PLAYER_BLACK), Ok(p_move));
+                let upper_case = notation.to_uppercase();
+                assert_eq!(Move::parse(&upper_case, PLAYER_BLACK), Ok(p_move));
+            }
         }
+        let pass = Move::pass(PLAYER_WHITE);
+        assert_eq!(pass.to_string(), "pass");
+        assert_eq!(Move::parse("pass", PLAYER_WHITE), Ok(pass));
+        assert_eq!(Move::parse(" PASS ", PLAYER_WHITE), Ok(pass));
     }
-    None
-}
 
+    #[test]
+    fn algebraic_notation_matches_the_tuple_notation() {
+        let d3 = Move::parse("d3", PLAYER_BLACK).unwrap();
+        assert_eq!((d3.row, d3.col), (2, 3));
+        assert_eq!(d3.tuple_notation(), "(3,4)");
+        assert_eq!(Move::parse("a1", PLAYER_BLACK).unwrap().tuple_notation(), "(1,1)");
+        assert_eq!(Move::parse("h8", PLAYER_BLACK).unwrap().tuple_notation(), "(8,8)");
+        assert_eq!(Move::pass(PLAYER_BLACK).tuple_notation(), "pass");
+    }
 
-fn vec_differs(fst: &[char], snd: &[char]) -> (bool, isize, char) {
-    for i in 0..fst.len() {
-        if fst[i] != snd[i] {
-            return (true, i as isize, snd[i]);
+    #[test]
+    fn malformed_moves_are_rejected() {
+        for notation in ["", "d", "d34", "passes"] {
+            assert_eq!(
+                Move::parse(notation, PLAYER_BLACK),
+                Err(ParseMoveError::BadFormat(notation.to_string()))
+            );
         }
+        assert_eq!(Move::parse("i3", PLAYER_BLACK), Err(ParseMoveError::InvalidColumn('i')));
+        assert_eq!(Move::parse("3d", PLAYER_BLACK), Err(ParseMoveError::InvalidColumn('3')));
+        assert_eq!(Move::parse("d9", PLAYER_BLACK), Err(ParseMoveError::InvalidRow('9')));
+        assert_eq!(Move::parse("d0", PLAYER_BLACK), Err(ParseMoveError::InvalidRow('0')));
     }
-
-    (false, -1, EMPTY_CELL)
 }
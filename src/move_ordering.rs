@@ -0,0 +1,84 @@
+//! This file contains the heuristics used to order the moves searched in
+//! interior nodes of the search tree. The better the ordering, the earlier
+//! the best move is searched and the more of the remaining moves are pruned.
+//! Moves are ordered by, in turn:
+//!
+//! 1. The best move stored in the transposition table.
+//! 2. The killer moves, quiet moves which caused a cutoff at the same ply.
+//! 3. The history score, which grows each time a move causes a cutoff.
+
+use crate::board::BOARD_SIZE;
+use crate::move_generator::Move;
+
+/// The number of killer moves remembered for each ply.
+pub const KILLERS_PER_PLY: usize = 2;
+
+/// The killer moves and history scores collected during a search.
+pub struct MoveOrdering {
+    killers: Vec<[Option<Move>; KILLERS_PER_PLY]>,
+    /// The history score of each square, for each player.
+    history: [[u32; BOARD_SIZE * BOARD_SIZE]; 2],
+}
+
+impl MoveOrdering {
+    /// Returns a new MoveOrdering without any killer moves or history.
+    pub fn new() -> MoveOrdering {
+        MoveOrdering {
+            killers: Vec::new(),
+            history: [[0; BOARD_SIZE * BOARD_SIZE]; 2],
+        }
+    }
+
+    /// Sorts the given moves so that the moves most likely to be best
+    /// come first.
+    ///
+    /// # Arguments
+    ///
+    /// * `moves` - The legal moves of the board, all belonging to the same player.
+    /// * `table_move` - The best move stored in the transposition table, if any.
+    /// * `ply` - The distance from the root of the search to the board.
+    /// * `max_player` - Whether the max player is the one making the moves.
+    pub fn order(&self, moves: &mut [Move], table_move: Option<Move>, ply: usize, max_player: bool) {
+        let killers = self.killers.get(ply);
+        let history = &self.history[max_player as usize];
+        moves.sort_by_cached_key(|p_move| {
+            let priority = if Some(*p_move) == table_move {
+                0
+            } else if killers.is_some_and(|k| k.contains(&Some(*p_move))) {
+                1
+            } else {
+                2
+            };
+            (priority, std::cmp::Reverse(history[p_move.row * BOARD_SIZE + p_move.col]))
+        });
+    }
+
+    /// Records that the given move caused a cutoff, making it a killer move
+    /// for the ply and increasing its history score.
+    ///
+    /// # Arguments
+    ///
+    /// * `p_move` - The move causing the cutoff.
+    /// * `ply` - The distance from the root of the search to the board.
+    /// * `depth` - The remaining depth of the search of the board.
+    /// * `max_player` - Whether the max player is the one making the move.
+    pub fn record_cutoff(&mut self, p_move: Move, ply: usize, depth: u32, max_player: bool) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLERS_PER_PLY]);
+        }
+        let killers = &mut self.killers[ply];
+        if !killers.contains(&Some(p_move)) {
+            killers.rotate_right(1);
+            killers[0] = Some(p_move);
+        }
+        // Cutoffs close to the root are rarer and more valuable.
+        let history = &mut self.history[max_player as usize][p_move.row * BOARD_SIZE + p_move.col];
+        *history = history.saturating_add(depth * depth);
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> MoveOrdering {
+        MoveOrdering::new()
+    }
+}
@@ -0,0 +1,296 @@
+//! This file contains the front end speaking the NBoard protocol, which lets
+//! the engine be used from Othello GUIs such as NBoard. The GUI sends one
+//! command per line, keeping the engine up to date with the game through
+//! `set game` and `move`, and asks it for moves with `go` and for the values
+//! of several moves with `hint`.
+//!
+//! After a game, `learn` adds its moves to the opening book, if one is used.
+//!
+//! Moves are written as an upper case square such as `F5`, or `PA` for a
+//! pass. Values are given in pieces from the perspective of the player to
+//! move, where heuristic values are scaled down to roughly match.
+
+use crate::alphabeta::{SearchConfig, SearchResult};
+use crate::board::{OthelloPosition, BOARD_SIZE};
+use crate::book::Book;
+use crate::engine::Engine;
+use crate::move_generator::{is_legal, Move};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The version of the protocol spoken.
+const PROTOCOL_VERSION: &str = "2";
+/// The name the engine reports to the GUI.
+const ENGINE_NAME: &str = "rustversai";
+/// The notation of a pass in the protocol.
+const PASS: &str = "PA";
+
+/// The state of the front end between commands.
+struct NBoard {
+    engine: Engine,
+    move_time_ms: u64,
+    /// The boards of the game since the last `set game`, each along with
+    /// the move made in it.
+    game: Vec<(OthelloPosition, Move)>,
+    /// The file of the opening book games are learned into, if any.
+    book_path: Option<PathBuf>,
+}
+
+/// Answers NBoard commands read from `input` by writing to `output`,
+/// until the input ends or `quit` is received.
+///
+/// # Arguments
+///
+/// * `input` - The commands of the GUI.
+/// * `output` - Where the answers are written.
+/// * `config` - The settings of the searches, whose maximum depth is changed by `set depth`.
+/// * `move_time_ms` - The time in milliseconds spent on each `go` or `hint`.
+/// * `book_path` - The file of the opening book `learn` adds games to, if any.
+pub fn run<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    config: SearchConfig,
+    move_time_ms: u64,
+    book_path: Option<&Path>,
+) -> io::Result<()> {
+    let mut nboard = NBoard {
+        engine: Engine::new(config),
+        move_time_ms,
+        game: Vec::new(),
+        book_path: book_path.map(Path::to_path_buf),
+    };
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let (command, rest) = match line.trim().split_once(' ') {
+            Some((command, rest)) => (command, rest.trim()),
+            None => (line.trim(), ""),
+        };
+        match command {
+            "" => {}
+            "quit" => return Ok(()),
+            "nboard" => {
+                if rest != PROTOCOL_VERSION {
+                    writeln!(output, "status Unsupported protocol version {}", rest)?;
+                }
+                writeln!(output, "set myname {}", ENGINE_NAME)?;
+            }
+            "set" => nboard.set(output, rest)?,
+            "move" => match parse_move(rest, nboard.engine.board()) {
+                Ok(p_move) => {
+                    nboard.game.push((nboard.engine.board().clone(), p_move));
+                    nboard.engine.play(&p_move);
+                }
+                Err(e) => writeln!(output, "status {}", e)?,
+            },
+            "go" => nboard.go(output)?,
+            "hint" => match rest.parse() {
+                Ok(count) => nboard.hint(output, count)?,
+                Err(_) => writeln!(output, "status Invalid hint count '{}'", rest)?,
+            },
+            "ping" => writeln!(output, "pong {}", rest)?,
+            "learn" => nboard.learn(output)?,
+            _ => writeln!(output, "status Unknown command '{}'", command)?,
+        }
+        output.flush()?;
+    }
+}
+
+impl NBoard {
+    /// Handles a `set` command, given everything after `set`.
+    fn set<W: Write>(&mut self, output: &mut W, rest: &str) -> io::Result<()> {
+        let (name, value) = rest.split_once(' ').unwrap_or((rest, ""));
+        match name {
+            "game" => match parse_game(value) {
+                Ok((board, game)) => {
+                    self.engine.set_board(board);
+                    self.game = game;
+                }
+                Err(e) => writeln!(output, "status Invalid game: {}", e)?,
+            },
+            "depth" => match value.trim().parse::<u32>() {
+                Ok(depth) if depth > 0 => self.engine.config.max_depth = depth,
+                _ => writeln!(output, "status Invalid depth '{}'", value)?,
+            },
+            // The search has no contempt, and other settings are not supported.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Adds the moves of the game to the opening book and saves it, if a
+    /// book is used and the game is over. The GUI waits for `learned`, so
+    /// it is written even if nothing could be learned.
+    fn learn<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        if let Some(path) = &self.book_path {
+            if self.engine.board().is_game_over() {
+                match learn_game(path, &self.game, self.engine.board()) {
+                    Ok(added) => writeln!(output, "status Learned {} moves", added)?,
+                    Err(e) => writeln!(output, "status Could not learn the game: {}", e)?,
+                }
+            } else {
+                writeln!(output, "status Only finished games are learned")?;
+            }
+        }
+        writeln!(output, "learned")
+    }
+
+    /// Searches the current board and writes the best move found along
+    /// with its value and the time taken, preceded by the search statistics.
+    fn go<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "status Thinking")?;
+        let start = Instant::now();
+        let result = self.engine.search(self.move_time_ms);
+        let seconds = start.elapsed().as_secs_f64();
+        writeln!(output, "nodestats {} {:.3}", result.nodes, seconds)?;
+        writeln!(output, "status Depth {}", result.depth)?;
+        match result.best_move {
+            Some(p_move) => writeln!(
+                output,
+                "=== {}/{:.2}/{:.3}",
+                format_move(&p_move),
+                result.score_in_discs(),
+                seconds
+            )?,
+            None => writeln!(output, "=== {}", PASS)?,
+        }
+        writeln!(output, "status")
+    }
+
+    /// Writes the value, depth and principal variation of the given number
+    /// of the best moves of the current board.
+    fn hint<W: Write>(&mut self, output: &mut W, count: usize) -> io::Result<()> {
+        writeln!(output, "status Thinking")?;
+        let results = self.engine.search_moves(self.move_time_ms);
+        for result in results.iter().take(count) {
+            writeln!(
+                output,
+                "search {} {:.2} 0 {}",
+                format_pv(result),
+                result.score_in_discs(),
+                result.depth
+            )?;
+        }
+        writeln!(output, "status")
+    }
+}
+
+/// Adds the moves of the game to the book in the file, which is created if
+/// it does not exist yet. Returns the number of moves added.
+fn learn_game(
+    path: &Path,
+    game: &[(OthelloPosition, Move)],
+    final_board: &OthelloPosition,
+) -> io::Result<usize> {
+    let mut book = if path.exists() {
+        Book::load(path)?
+    } else {
+        Book::new()
+    };
+    let added = book.learn(game, final_board);
+    book.save(path)?;
+
+    Ok(added)
+}
+
+/// Returns the move in the notation of the protocol.
+fn format_move(p_move: &Move) -> String {
+    if p_move.is_pass() {
+        String::from(PASS)
+    } else {
+        p_move.to_string().to_uppercase()
+    }
+}
+
+/// Returns the principal variation of the result as its moves written
+/// one after the other, such as `F5D6C3`.
+fn format_pv(result: &SearchResult) -> String {
+    result.pv.iter().map(format_move).collect()
+}
+
+/// Parses a move as sent by the GUI, a square or pass optionally followed
+/// by `/` and the value and time of the move, and checks that it is legal.
+fn parse_move(notation: &str, board: &OthelloPosition) -> Result<Move, String> {
+    let square = notation.split('/').next().unwrap_or("").trim();
+    let p_move = if square.eq_ignore_ascii_case(PASS) {
+        Move::pass(board.to_move())
+    } else {
+        Move::parse(square, board.to_move())
+            .map_err(|e| format!("Invalid move '{}': {}", square, e))?
+    };
+    if is_legal(board, &p_move) {
+        Ok(p_move)
+    } else {
+        Err(format!("Illegal move '{}'", square))
+    }
+}
+
+/// Parses a game in the Generic Game Format and returns the board reached
+/// after its moves, along with the boards of the game each paired with the
+/// move made in it. The game is made up of tags such as `PB[name]`, of
+/// which only the starting board `BO` and the black and white moves `B`
+/// and `W` are used. The board is given as `8`, the 64 squares row by row
+/// as `-`, `*` (black) or `O` (white), and the player to move.
+fn parse_game(game: &str) -> Result<(OthelloPosition, Vec<(OthelloPosition, Move)>), String> {
+    let mut board = None;
+    let mut moves = Vec::new();
+    let mut rest = game;
+    while let Some(open) = rest.find('[') {
+        let name = rest[..open]
+            .trim_start_matches(|c: char| !c.is_ascii_alphabetic())
+            .trim();
+        let close = match rest[open..].find(']') {
+            Some(close) => open + close,
+            None => return Err(format!("unterminated tag '{}'", name)),
+        };
+        let value = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+        match name {
+            "BO" => board = Some(parse_ggf_board(value)?),
+            "B" | "W" => {
+                let current = board.as_mut().ok_or("a move comes before the board")?;
+                if (name == "W") != current.max_player() {
+                    return Err(format!("{} moves out of turn", name));
+                }
+                let p_move = parse_move(value, current)?;
+                moves.push((current.clone(), p_move));
+                *current = Move::make_move(current, &p_move);
+            }
+            _ => {}
+        }
+    }
+
+    board
+        .map(|board| (board, moves))
+        .ok_or_else(|| String::from("the board is missing"))
+}
+
+/// Parses the value of the `BO` tag of a game in the Generic Game Format.
+fn parse_ggf_board(value: &str) -> Result<OthelloPosition, String> {
+    let chars: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let squares = BOARD_SIZE * BOARD_SIZE;
+    if chars.len() != squares + 2 || chars[0] != '8' {
+        return Err(format!("expected an 8x8 board but found '{}'", value));
+    }
+    let (mut white, mut black) = (0, 0);
+    for (square, c) in chars[1..=squares].iter().enumerate() {
+        match c {
+            '*' | 'X' | 'x' => black |= 1 << square,
+            'O' | 'o' => white |= 1 << square,
+            '-' | '.' => {}
+            _ => return Err(format!("unknown square '{}'", c)),
+        }
+    }
+    let max_player = match chars[squares + 1] {
+        '*' | 'X' | 'x' => false,
+        'O' | 'o' => true,
+        c => return Err(format!("unknown player to move '{}'", c)),
+    };
+
+    Ok(OthelloPosition::from_colours(white, black, max_player))
+}
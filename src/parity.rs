@@ -0,0 +1,82 @@
+//! This file contains the parity of the empty regions of the board. Late in
+//! the game the empty squares split up into regions, groups of empty squares
+//! connected to each other, where the players tend to take turns. The player
+//! making the first move in a region with an odd number of empty squares is
+//! then likely to also make the last, which cannot be answered within the
+//! region, so having the first move in the odd regions is an advantage.
+
+use crate::move_generator::neighbours;
+
+/// An iterator over the regions of a set of empty squares, where squares
+/// are connected if they are next to each other in any of the eight
+/// directions. Each region is given as a bitboard.
+pub struct Regions {
+    remaining: u64,
+}
+
+impl Iterator for Regions {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // Flood fill from the lowest remaining square.
+        let mut region = self.remaining & self.remaining.wrapping_neg();
+        loop {
+            let adjacent = neighbours(region).iter().fold(region, |adjacent, n| adjacent | n);
+            let grown = self.remaining & adjacent;
+            if grown == region {
+                break;
+            }
+            region = grown;
+        }
+        self.remaining &= !region;
+
+        Some(region)
+    }
+}
+
+/// Returns an iterator over the regions of the given empty squares.
+///
+/// # Arguments
+///
+/// * `empty` - The empty squares of the board.
+pub fn regions(empty: u64) -> Regions {
+    Regions { remaining: empty }
+}
+
+/// Returns a bitboard of the squares of the regions of the given empty
+/// squares which hold an odd number of squares.
+///
+/// # Arguments
+///
+/// * `empty` - The empty squares of the board.
+pub fn odd_regions(empty: u64) -> u64 {
+    regions(empty)
+        .filter(|region| region.count_ones() % 2 == 1)
+        .fold(0, |odd, region| odd | region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::square_bit;
+
+    #[test]
+    fn regions_are_connected_in_all_eight_directions() {
+        let diagonal = square_bit(3, 3) | square_bit(4, 4);
+        let empty = square_bit(0, 0) | diagonal | square_bit(7, 7);
+        let found: Vec<u64> = regions(empty).collect();
+        assert_eq!(found, vec![square_bit(0, 0), diagonal, square_bit(7, 7)]);
+    }
+
+    #[test]
+    fn odd_regions_leaves_out_even_regions() {
+        let pair = square_bit(0, 0) | square_bit(0, 1);
+        let triple = square_bit(5, 5) | square_bit(6, 6) | square_bit(7, 7);
+        assert_eq!(odd_regions(pair | triple), triple);
+        assert_eq!(odd_regions(0), 0);
+        assert_eq!(odd_regions(u64::MAX), 0);
+    }
+}
@@ -0,0 +1,52 @@
+//! This file contains perft, which counts the boards reachable from a
+//! board in a given number of moves. The counts from the starting position
+//! are well known, which makes them a check of the move generation: any
+//! missing, extra or wrongly played move changes the count.
+//!
+//! A pass counts as a move of its own, while a finished game has no
+//! children and therefore only counts once no moves remain to be made.
+
+use crate::board::OthelloPosition;
+use crate::move_generator::Move;
+
+/// Returns the number of move sequences of exactly the given length which
+/// can be played from the board.
+///
+/// # Arguments
+///
+/// * `board` - The board to count from.
+/// * `depth` - The number of moves in each sequence.
+pub fn perft(board: &OthelloPosition, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let children = board.generate_children();
+    if depth == 1 {
+        return children.len() as u64;
+    }
+
+    children
+        .iter()
+        .map(|(_, child)| perft(child, depth - 1))
+        .sum()
+}
+
+/// Returns the perft count of each child of the board, along with the
+/// move leading to it. The counts add up to the perft count of the board,
+/// so comparing them narrows a wrong count down to a single move.
+///
+/// # Arguments
+///
+/// * `board` - The board to count from.
+/// * `depth` - The number of moves in each sequence, including the first.
+pub fn divide(board: &OthelloPosition, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    board
+        .generate_children()
+        .into_iter()
+        .map(|(p_move, child)| (p_move, perft(&child, depth - 1)))
+        .collect()
+}
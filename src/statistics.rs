@@ -0,0 +1,234 @@
+//! This file contains the statistics used to judge the results of a match.
+//! The score of a player, the share of the points it won, is turned into an
+//! Elo difference along with a confidence interval. Changes to the engine
+//! are tested with a sequential probability ratio test (SPRT), which decides
+//! between two hypotheses about the Elo difference after every game pair,
+//! and so stops as soon as the games played give enough evidence either way.
+//!
+//! Both use the normal approximation of the score, with the variance
+//! estimated from the wins, draws and losses actually played.
+//!
+//! The results can also be written as JSON, for scripts running matches.
+
+use crate::match_runner::MatchSummary;
+
+/// The number of decimals of the statistics written as JSON.
+const JSON_DECIMALS: usize = 2;
+
+/// The number of standard errors on each side of the mean covered by
+/// a 95% confidence interval.
+const CONFIDENCE_95: f64 = 1.959964;
+
+/// Returns the Elo difference at which the expected share of the points
+/// is the given score. A score of 0 or 1 gives an infinite difference.
+pub fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Returns the expected share of the points of a player with the given
+/// Elo difference to its opponent.
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The Elo difference of a match along with its confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    /// The lower end of the 95% confidence interval.
+    pub lower: f64,
+    /// The upper end of the 95% confidence interval.
+    pub upper: f64,
+}
+
+impl EloEstimate {
+    /// Returns the estimated Elo difference of the first player of the match
+    /// to the second, with a 95% confidence interval.
+    pub fn from_summary(summary: &MatchSummary) -> EloEstimate {
+        let score = summary.score();
+        let margin = CONFIDENCE_95 * (score_variance(summary) / summary.games().max(1) as f64).sqrt();
+        EloEstimate {
+            elo: elo_difference(score),
+            lower: elo_difference((score - margin).max(0.0)),
+            upper: elo_difference((score + margin).min(1.0)),
+        }
+    }
+}
+
+/// Returns the variance of the score of a single game of the match.
+fn score_variance(summary: &MatchSummary) -> f64 {
+    let games = summary.games();
+    if games == 0 {
+        return 0.0;
+    }
+    let score = summary.score();
+    let squared_deviations = summary.wins as f64 * (1.0 - score).powi(2)
+        + summary.draws as f64 * (0.5 - score).powi(2)
+        + summary.losses as f64 * score.powi(2);
+    squared_deviations / games as f64
+}
+
+/// The decision of a sequential probability ratio test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtDecision {
+    /// The Elo difference is elo1 rather than elo0: the change is stronger.
+    AcceptH1,
+    /// The Elo difference is elo0 rather than elo1: the change is not stronger.
+    AcceptH0,
+    /// More games are needed to decide.
+    Continue,
+}
+
+/// A sequential probability ratio test of whether the first player of a
+/// match is elo1 rather than elo0 Elo stronger than the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    /// The Elo difference of the null hypothesis.
+    pub elo0: f64,
+    /// The Elo difference of the alternative hypothesis.
+    pub elo1: f64,
+    /// The probability of accepting H1 when H0 is true.
+    pub alpha: f64,
+    /// The probability of accepting H0 when H1 is true.
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Returns a new test between the given Elo differences, with the given
+    /// probabilities of a false positive and a false negative.
+    ///
+    /// # Arguments
+    ///
+    /// * `elo0` - The Elo difference of the null hypothesis.
+    /// * `elo1` - The Elo difference of the alternative hypothesis, above elo0.
+    /// * `alpha` - The probability of accepting H1 when H0 is true.
+    /// * `beta` - The probability of accepting H0 when H1 is true.
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        }
+    }
+
+    /// Returns the log-likelihood ratio of H1 to H0 given the results.
+    /// Until the results vary the ratio can not be estimated and is 0.
+    pub fn llr(&self, summary: &MatchSummary) -> f64 {
+        let variance = score_variance(summary);
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        summary.games() as f64 * (score1 - score0) * (2.0 * summary.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+
+    /// Returns the log-likelihood ratio at or below which H0 is accepted.
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1.0 - self.alpha)).ln()
+    }
+
+    /// Returns the log-likelihood ratio at or above which H1 is accepted.
+    pub fn upper_bound(&self) -> f64 {
+        ((1.0 - self.beta) / self.alpha).ln()
+    }
+
+    /// Returns which hypothesis the results support, if either.
+    pub fn decide(&self, summary: &MatchSummary) -> SprtDecision {
+        let llr = self.llr(summary);
+        if llr >= self.upper_bound() {
+            SprtDecision::AcceptH1
+        } else if llr <= self.lower_bound() {
+            SprtDecision::AcceptH0
+        } else {
+            SprtDecision::Continue
+        }
+    }
+}
+
+/// Returns the results of a match, along with the Elo estimate and the
+/// state of the SPRT if one is given, as a single JSON object. Infinite
+/// Elo differences, from matches without wins or without losses, are null.
+///
+/// # Arguments
+///
+/// * `summary` - The results of the match.
+/// * `sprt` - The test run during the match, if any.
+pub fn match_json(summary: &MatchSummary, sprt: Option<&Sprt>) -> String {
+    let rounded = |value: f64| json_number(value, Some(JSON_DECIMALS));
+    let estimate = EloEstimate::from_summary(summary);
+    let mut fields = vec![
+        format!("\"games\":{}", summary.games()),
+        format!("\"wins\":{}", summary.wins),
+        format!("\"draws\":{}", summary.draws),
+        format!("\"losses\":{}", summary.losses),
+        format!("\"score\":{}", rounded(summary.score())),
+        format!("\"elo\":{}", rounded(estimate.elo)),
+        format!("\"elo_lower\":{}", rounded(estimate.lower)),
+        format!("\"elo_upper\":{}", rounded(estimate.upper)),
+    ];
+    if let Some(sprt) = sprt {
+        let decision = match sprt.decide(summary) {
+            SprtDecision::AcceptH1 => "H1",
+            SprtDecision::AcceptH0 => "H0",
+            SprtDecision::Continue => "continue",
+        };
+        let sprt_fields = [
+            format!("\"elo0\":{}", json_number(sprt.elo0, None)),
+            format!("\"elo1\":{}", json_number(sprt.elo1, None)),
+            format!("\"alpha\":{}", json_number(sprt.alpha, None)),
+            format!("\"beta\":{}", json_number(sprt.beta, None)),
+            format!("\"llr\":{}", rounded(sprt.llr(summary))),
+            format!("\"lower_bound\":{}", rounded(sprt.lower_bound())),
+            format!("\"upper_bound\":{}", rounded(sprt.upper_bound())),
+            format!("\"decision\":\"{}\"", decision),
+        ];
+        fields.push(format!("\"sprt\":{{{}}}", sprt_fields.join(",")));
+    }
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Returns the value as a JSON number, rounded to the given number of
+/// decimals if any, or null if it is not finite, which JSON can not represent.
+fn json_number(value: f64, decimals: Option<usize>) -> String {
+    match decimals {
+        _ if !value.is_finite() => String::from("null"),
+        Some(decimals) => format!("{:.*}", decimals, value),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_holds_the_results_and_the_sprt() {
+        let summary = MatchSummary {
+            wins: 3,
+            draws: 0,
+            losses: 1,
+        };
+        let sprt = Sprt::new(0.0, 10.0, 0.001, 0.05);
+        let json = match_json(&summary, Some(&sprt));
+        assert!(json.starts_with("{\"games\":4,\"wins\":3,\"draws\":0,\"losses\":1,"));
+        assert!(json.contains("\"score\":0.75,"));
+        assert!(json.contains("\"sprt\":{\"elo0\":0,\"elo1\":10,\"alpha\":0.001,\"beta\":0.05,"));
+        assert!(json.ends_with("\"decision\":\"continue\"}}"));
+    }
+
+    #[test]
+    fn json_writes_infinite_values_as_null() {
+        let summary = MatchSummary {
+            wins: 2,
+            draws: 0,
+            losses: 0,
+        };
+        let json = match_json(&summary, Some(&Sprt::new(f64::NEG_INFINITY, 0.0, 0.05, 0.05)));
+        assert!(json.contains("\"elo\":null,\"elo_lower\":"));
+        assert!(json.contains("\"elo0\":null,"));
+        assert!(!json.contains("inf"));
+    }
+}
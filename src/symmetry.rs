@@ -0,0 +1,200 @@
+//! This file contains the symmetries of the board. The board looks the same
+//! after being rotated by a quarter turn or reflected, so every board has up
+//! to eight orientations which are equally good for the player to move. The
+//! symmetries are numbered 0 to 7, where each of the three bits of the number
+//! selects a step, applied in order: bit 2 transposes the board (swaps rows
+//! and columns), bit 1 flips it upside down, and bit 0 mirrors it left to
+//! right. Symmetry 0 leaves the board as it is.
+//!
+//! The canonical orientation of a board is the one with the smallest
+//! bitboards, which lets all orientations of a board share a single key.
+//! Some boards look the same in several orientations, such as the starting
+//! position, which is left unchanged by four of the symmetries and so has
+//! only two distinct orientations, in which all four first moves are equal.
+
+use crate::board::{OthelloPosition, BOARD_SIZE};
+use crate::move_generator::Move;
+
+/// The number of symmetries of the board.
+pub const SYMMETRIES: usize = 8;
+
+/// The symmetry undoing each symmetry.
+const INVERSES: [usize; SYMMETRIES] = [0, 1, 2, 3, 4, 6, 5, 7];
+
+/// Returns the bitboard with every square moved by the given symmetry.
+///
+/// # Arguments
+///
+/// * `bits` - The bitboard to move.
+/// * `symmetry` - The number of the symmetry, from 0 to 7.
+pub fn transform_bits(mut bits: u64, symmetry: usize) -> u64 {
+    if symmetry & 4 != 0 {
+        bits = transpose(bits);
+    }
+    if symmetry & 2 != 0 {
+        bits = bits.swap_bytes();
+    }
+    if symmetry & 1 != 0 {
+        bits = mirror(bits);
+    }
+
+    bits
+}
+
+/// Returns the index of the square the given square is moved to by the symmetry.
+///
+/// # Arguments
+///
+/// * `square` - The index of the square, `row * 8 + col`.
+/// * `symmetry` - The number of the symmetry, from 0 to 7.
+pub fn transform_square(square: usize, symmetry: usize) -> usize {
+    transform_bits(1 << square, symmetry).trailing_zeros() as usize
+}
+
+/// Returns the symmetry undoing the given symmetry.
+pub fn inverse(symmetry: usize) -> usize {
+    INVERSES[symmetry]
+}
+
+/// Returns the board rotated or reflected by the given symmetry.
+///
+/// # Arguments
+///
+/// * `board` - The board to move.
+/// * `symmetry` - The number of the symmetry, from 0 to 7.
+pub fn transform_position(board: &OthelloPosition, symmetry: usize) -> OthelloPosition {
+    OthelloPosition::from_colours(
+        transform_bits(board.white(), symmetry),
+        transform_bits(board.black(), symmetry),
+        board.max_player(),
+    )
+}
+
+/// Returns the move made on the square the move's square is moved to by
+/// the symmetry. A pass is left as it is.
+///
+/// # Arguments
+///
+/// * `p_move` - The move to move.
+/// * `symmetry` - The number of the symmetry, from 0 to 7.
+pub fn transform_move(p_move: &Move, symmetry: usize) -> Move {
+    if p_move.is_pass() {
+        return *p_move;
+    }
+    let square = transform_square(p_move.row * BOARD_SIZE + p_move.col, symmetry);
+
+    Move::new(p_move.player(), square / BOARD_SIZE, square % BOARD_SIZE)
+}
+
+/// Returns the canonical orientation of the board, along with the
+/// symmetry moving the board to it.
+pub fn canonical(board: &OthelloPosition) -> (OthelloPosition, usize) {
+    let (white, black) = (board.white(), board.black());
+    let symmetry = (0..SYMMETRIES)
+        .min_by_key(|&symmetry| {
+            (
+                transform_bits(white, symmetry),
+                transform_bits(black, symmetry),
+            )
+        })
+        .unwrap_or(0);
+
+    (transform_position(board, symmetry), symmetry)
+}
+
+/// Swaps the rows and columns of the bitboard, moving square `(row, col)`
+/// to `(col, row)`.
+fn transpose(mut bits: u64) -> u64 {
+    const K1: u64 = 0x5500_5500_5500_5500;
+    const K2: u64 = 0x3333_0000_3333_0000;
+    const K4: u64 = 0x0f0f_0f0f_0000_0000;
+    let mut t = K4 & (bits ^ (bits << 28));
+    bits ^= t ^ (t >> 28);
+    t = K2 & (bits ^ (bits << 14));
+    bits ^= t ^ (t >> 14);
+    t = K1 & (bits ^ (bits << 7));
+    bits ^= t ^ (t >> 7);
+
+    bits
+}
+
+/// Reverses the order of the columns of the bitboard.
+fn mirror(mut bits: u64) -> u64 {
+    const K1: u64 = 0x5555_5555_5555_5555;
+    const K2: u64 = 0x3333_3333_3333_3333;
+    const K4: u64 = 0x0f0f_0f0f_0f0f_0f0f;
+    bits = ((bits >> 1) & K1) | ((bits & K1) << 1);
+    bits = ((bits >> 2) & K2) | ((bits & K2) << 2);
+    ((bits >> 4) & K4) | ((bits & K4) << 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_generator::get_moves;
+
+    /// A board from the middle of a game, which looks different in every orientation.
+    const POSITION: &str = "BOXXXXXEXOXXXXOXEOXXXXOXEOOOXXOXEXXOXXOEEEXEXOXEEOOOOOOOOEEEXOEEE";
+
+    #[test]
+    fn inverse_undoes_every_symmetry() {
+        for symmetry in 0..SYMMETRIES {
+            for square in 0..BOARD_SIZE * BOARD_SIZE {
+                let moved = transform_square(square, symmetry);
+                assert_eq!(transform_square(moved, inverse(symmetry)), square);
+            }
+        }
+    }
+
+    #[test]
+    fn symmetries_move_squares_as_described() {
+        // Square (row 1, col 2) under transposing, flipping and mirroring.
+        let square = BOARD_SIZE + 2;
+        assert_eq!(transform_square(square, 4), 2 * BOARD_SIZE + 1);
+        assert_eq!(transform_square(square, 2), 6 * BOARD_SIZE + 2);
+        assert_eq!(transform_square(square, 1), BOARD_SIZE + 5);
+        assert_eq!(transform_square(square, 0), square);
+    }
+
+    #[test]
+    fn symmetries_are_distinct_and_keep_pieces() {
+        let board: OthelloPosition = POSITION.parse().unwrap();
+        let mut seen = Vec::new();
+        for symmetry in 0..SYMMETRIES {
+            let moved = transform_bits(board.white(), symmetry);
+            assert_eq!(moved.count_ones(), board.white().count_ones());
+            assert!(!seen.contains(&moved));
+            seen.push(moved);
+        }
+    }
+
+    #[test]
+    fn legal_moves_are_moved_with_the_board() {
+        let board: OthelloPosition = POSITION.parse().unwrap();
+        let key = |p_move: &Move| (p_move.row, p_move.col);
+        for symmetry in 0..SYMMETRIES {
+            let mut expected: Vec<Move> = get_moves(&board)
+                .iter()
+                .map(|p_move| transform_move(p_move, symmetry))
+                .collect();
+            let mut moves = get_moves(&transform_position(&board, symmetry));
+            expected.sort_by_key(key);
+            moves.sort_by_key(key);
+            assert_eq!(moves, expected);
+        }
+        let pass = Move::pass(board.to_move());
+        assert_eq!(transform_move(&pass, 5), pass);
+    }
+
+    #[test]
+    fn every_orientation_has_the_same_canonical_board() {
+        let board: OthelloPosition = POSITION.parse().unwrap();
+        let (expected, _) = canonical(&board);
+        for symmetry in 0..SYMMETRIES {
+            let moved = transform_position(&board, symmetry);
+            let (canonical_board, to_canonical) = canonical(&moved);
+            assert_eq!(canonical_board.hash(), expected.hash());
+            assert_eq!(transform_position(&moved, to_canonical), canonical_board);
+        }
+    }
+}
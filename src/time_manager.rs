@@ -0,0 +1,127 @@
+//! This file contains the TimeManager, which keeps track of how much of
+//! the time budget for a search has been used. It has two deadlines:
+//! the soft limit, after which no new iteration of the iterative deepening
+//! search is started since it would be unlikely to finish, and the hard
+//! limit, after which the current iteration is aborted so that a move can
+//! be reported before the budget runs out. The search can also be stopped
+//! explicitly, which is used to stop helper threads once the main search
+//! thread is done.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The percentage of the budget after which no new iteration is started.
+pub const SOFT_LIMIT_PERCENT: u64 = 50;
+/// The percentage of the budget after which the current iteration is aborted.
+/// The remainder is left for reporting the move.
+pub const HARD_LIMIT_PERCENT: u64 = 95;
+
+/// Keeps track of the time used by a search.
+#[derive(Debug)]
+pub struct TimeManager {
+    start_time: Instant,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    stopped: AtomicBool,
+}
+
+impl TimeManager {
+    /// Returns a new TimeManager for the given budget, with the soft and
+    /// hard limits placed at the default percentages of the budget. Budgets
+    /// too large to take a percentage of are treated as unlimited.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_time` - The Instant the budget started being used.
+    /// * `budget_ms` - The total time available in milliseconds.
+    pub fn new(start_time: Instant, budget_ms: u64) -> TimeManager {
+        TimeManager::with_limits(
+            start_time,
+            budget_ms.saturating_mul(SOFT_LIMIT_PERCENT) / 100,
+            budget_ms.saturating_mul(HARD_LIMIT_PERCENT) / 100,
+        )
+    }
+
+    /// Returns a new TimeManager with explicitly set limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_time` - The Instant the budget started being used.
+    /// * `soft_limit_ms` - The time in milliseconds after which no new iteration is started.
+    /// * `hard_limit_ms` - The time in milliseconds after which the current iteration is aborted.
+    pub fn with_limits(start_time: Instant, soft_limit_ms: u64, hard_limit_ms: u64) -> TimeManager {
+        TimeManager {
+            start_time,
+            soft_limit: Duration::from_millis(soft_limit_ms),
+            hard_limit: Duration::from_millis(hard_limit_ms),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns a TimeManager with the same budget and limits, which can be
+    /// stopped without stopping this one. It starts out stopped if this
+    /// one has been stopped.
+    pub fn detached(&self) -> TimeManager {
+        TimeManager {
+            start_time: self.start_time,
+            soft_limit: self.soft_limit,
+            hard_limit: self.hard_limit,
+            stopped: AtomicBool::new(self.is_stopped()),
+        }
+    }
+
+    /// Returns the time passed since the start of the budget.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(self.start_time)
+    }
+
+    /// Returns whether there is enough time left to start a new iteration.
+    pub fn can_start_iteration(&self) -> bool {
+        !self.is_stopped() && self.elapsed() < self.soft_limit
+    }
+
+    /// Returns whether the current iteration has to be aborted.
+    pub fn is_out_of_time(&self) -> bool {
+        self.is_stopped() || self.elapsed() >= self.hard_limit
+    }
+
+    /// Makes every search using this TimeManager stop, as if the hard limit was reached.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_are_percentages_of_the_budget() {
+        let time = TimeManager::new(Instant::now(), 1000);
+        assert_eq!(time.soft_limit, Duration::from_millis(500));
+        assert_eq!(time.hard_limit, Duration::from_millis(950));
+    }
+
+    #[test]
+    fn stopping_a_detached_time_manager_leaves_the_original_running() {
+        let time = TimeManager::new(Instant::now(), 60_000);
+        let detached = time.detached();
+        detached.stop();
+        assert!(detached.is_out_of_time());
+        assert!(!time.is_out_of_time());
+        time.stop();
+        assert!(time.detached().is_out_of_time());
+    }
+
+    #[test]
+    fn huge_budgets_do_not_overflow() {
+        let time = TimeManager::new(Instant::now(), u64::MAX);
+        assert!(time.soft_limit <= time.hard_limit);
+        assert!(time.can_start_iteration());
+        assert!(!time.is_out_of_time());
+    }
+}
@@ -0,0 +1,239 @@
+//! This file contains the transposition table used by the search to
+//! remember the values of boards it has already searched. Boards are
+//! identified by their Zobrist hash, which also decides the slot of the
+//! fixed-size table an entry is stored in.
+//!
+//! The table is shared by all search threads without locking. Each slot
+//! holds two atomic words: the entry packed into 64 bits, and the hash
+//! XOR:ed with the packed entry. A slot torn by two threads writing at
+//! once no longer XORs back to the hash, so it is simply treated as a miss.
+
+use crate::board::{BOARD_SIZE, PLAYER_BLACK, PLAYER_WHITE};
+use crate::move_generator::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The number of entries in the table used by default, 2^20 (16 MiB).
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 20;
+
+/// The greatest depth which can be stored, see Entry::depth.
+const MAX_STORED_DEPTH: u32 = 254;
+/// The stored depth representing a depth greater than MAX_STORED_DEPTH.
+const UNLIMITED_DEPTH: u64 = 255;
+/// The stored square representing the lack of a best move.
+const NO_MOVE: u64 = 64;
+
+/// Describes how a stored value relates to the true value of a board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    /// The value is the true value of the board.
+    Exact,
+    /// The search failed high, the true value is at least the value.
+    Lower,
+    /// The search failed low, the true value is at most the value.
+    Upper,
+}
+
+/// A single stored search result.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    /// The full hash of the board, used to detect collisions between boards
+    /// sharing the same slot.
+    pub key: u64,
+    /// The remaining depth the board was searched to. Depths above 254,
+    /// other than u32::MAX, are stored as 254.
+    pub depth: u32,
+    pub bound: Bound,
+    /// The value of the board, which must fit in 32 bits.
+    pub value: isize,
+    /// The best move found in the board, if any.
+    pub best_move: Option<Move>,
+}
+
+impl Entry {
+    /// Packs everything but the key into 64 bits.
+    fn pack(&self) -> u64 {
+        let depth = match self.depth {
+            u32::MAX => UNLIMITED_DEPTH,
+            depth => depth.min(MAX_STORED_DEPTH) as u64,
+        };
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let (square, white) = match self.best_move {
+            Some(p_move) if !p_move.is_pass() => (
+                (p_move.row * BOARD_SIZE + p_move.col) as u64,
+                (p_move.player() == PLAYER_WHITE) as u64,
+            ),
+            _ => (NO_MOVE, 0),
+        };
+
+        (self.value as i32 as u32 as u64)
+            | depth << 32
+            | bound << 40
+            | square << 42
+            | white << 49
+    }
+
+    /// The inverse of pack, for the board with the given hash.
+    fn unpack(key: u64, data: u64) -> Entry {
+        let depth = match (data >> 32) & 0xff {
+            UNLIMITED_DEPTH => u32::MAX,
+            depth => depth as u32,
+        };
+        let bound = match (data >> 40) & 0x3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let square = ((data >> 42) & 0x7f) as usize;
+        let best_move = if square as u64 == NO_MOVE {
+            None
+        } else {
+            let player = if (data >> 49) & 1 == 1 {
+                PLAYER_WHITE
+            } else {
+                PLAYER_BLACK
+            };
+            Some(Move::new(player, square / BOARD_SIZE, square % BOARD_SIZE))
+        };
+
+        Entry {
+            key,
+            depth,
+            bound,
+            value: data as u32 as i32 as isize,
+            best_move,
+        }
+    }
+}
+
+/// A fixed-size table of search results indexed by Zobrist hash,
+/// which can be shared between threads.
+pub struct TranspositionTable {
+    /// The hash XOR:ed with the packed entry, followed by the packed entry.
+    slots: Vec<[AtomicU64; 2]>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+    /// Returns a new, empty table with room for the given number of entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The number of entries, rounded up to the nearest power of two.
+    pub fn new(size: usize) -> TranspositionTable {
+        let size = size.max(1).next_power_of_two();
+        TranspositionTable {
+            slots: (0..size)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+            mask: size - 1,
+        }
+    }
+
+    /// Returns the entry stored for the board with the given hash, if any.
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = &self.slots[key as usize & self.mask];
+        let checked_key = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+        // No stored entry has a depth of zero, so empty slots have no data.
+        if data != 0 && checked_key ^ data == key {
+            Some(Entry::unpack(key, data))
+        } else {
+            None
+        }
+    }
+
+    /// Stores the given entry. An entry for the same board is always
+    /// replaced, while an entry for another board sharing the slot
+    /// is only replaced if it was not searched deeper.
+    pub fn store(&self, entry: Entry) {
+        let slot = &self.slots[entry.key as usize & self.mask];
+        let old_data = slot[1].load(Ordering::Relaxed);
+        if old_data != 0 {
+            let old = Entry::unpack(slot[0].load(Ordering::Relaxed) ^ old_data, old_data);
+            if old.key != entry.key && old.depth > entry.depth {
+                return;
+            }
+        }
+        let data = entry.pack();
+        slot[0].store(entry.key ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new(DEFAULT_TABLE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: u64 = 0x0123_4567_89ab_cdef;
+
+    fn entry(depth: u32, value: isize, best_move: Option<Move>) -> Entry {
+        Entry {
+            key: KEY,
+            depth,
+            bound: Bound::Lower,
+            value,
+            best_move,
+        }
+    }
+
+    fn round_trip(entry: &Entry) -> Entry {
+        Entry::unpack(entry.key, entry.pack())
+    }
+
+    #[test]
+    fn pack_keeps_negative_values() {
+        for value in [-1, -64, -(i32::MAX as isize), i32::MIN as isize] {
+            let unpacked = round_trip(&entry(3, value, None));
+            assert_eq!(unpacked.value, value);
+            assert_eq!(unpacked.depth, 3);
+            assert_eq!(unpacked.bound, Bound::Lower);
+        }
+    }
+
+    #[test]
+    fn pack_keeps_unlimited_depth_and_caps_other_depths() {
+        assert_eq!(round_trip(&entry(u32::MAX, 0, None)).depth, u32::MAX);
+        assert_eq!(
+            round_trip(&entry(MAX_STORED_DEPTH, 0, None)).depth,
+            MAX_STORED_DEPTH
+        );
+        assert_eq!(round_trip(&entry(1000, 0, None)).depth, MAX_STORED_DEPTH);
+    }
+
+    #[test]
+    fn pack_keeps_the_best_move_and_its_colour() {
+        assert_eq!(round_trip(&entry(1, 5, None)).best_move, None);
+        for p_move in [
+            Move::new(PLAYER_WHITE, 0, 0),
+            Move::new(PLAYER_BLACK, 7, 7),
+            Move::new(PLAYER_BLACK, 2, 3),
+        ] {
+            let unpacked = round_trip(&entry(1, 5, Some(p_move)));
+            assert_eq!(unpacked.best_move, Some(p_move));
+        }
+        // A pass is stored as the lack of a best move.
+        let pass = Move::pass(PLAYER_WHITE);
+        assert_eq!(round_trip(&entry(1, 5, Some(pass))).best_move, None);
+    }
+
+    #[test]
+    fn probe_only_finds_entries_for_the_same_key() {
+        let table = TranspositionTable::new(16);
+        table.store(entry(4, -7, Some(Move::new(PLAYER_WHITE, 2, 3))));
+        let found = table.probe(KEY).unwrap();
+        assert_eq!((found.depth, found.value), (4, -7));
+        // Another key sharing the slot must not see the entry.
+        assert!(table.probe(KEY ^ 1 << 40).is_none());
+        assert!(TranspositionTable::new(16).probe(KEY).is_none());
+    }
+}
@@ -0,0 +1,100 @@
+//! This file contains the random keys used for Zobrist hashing of
+//! boards. The hash of a board is the XOR of the key of every piece
+//! on it, along with an extra key if the max player is to go next.
+//! Since XOR is its own inverse, the hash can be updated incrementally
+//! when pieces are placed or flipped.
+
+use crate::board::BOARD_SIZE;
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+
+/// The key of a white piece on each square.
+pub const WHITE_KEYS: [u64; SQUARES] = generate_keys(0x5eed_0fe1_0000_0000);
+/// The key of a black piece on each square.
+pub const BLACK_KEYS: [u64; SQUARES] = generate_keys(0x5eed_0fb1_0000_0000);
+/// The XOR of the white and black key of each square, used to flip a piece.
+pub const FLIP_KEYS: [u64; SQUARES] = combine_keys(&WHITE_KEYS, &BLACK_KEYS);
+/// The key added when the max player is to go next.
+pub const MAX_PLAYER_KEY: u64 = splitmix64(0x5eed_0f51_de00_0000);
+
+/// Computes the hash of a board from scratch.
+///
+/// # Arguments
+///
+/// * `white` - The pieces of the white (max) player.
+/// * `black` - The pieces of the black (min) player.
+/// * `max_player` - Whether the max player is to go next.
+pub fn hash(white: u64, black: u64, max_player: bool) -> u64 {
+    let mut hash = if max_player { MAX_PLAYER_KEY } else { 0 };
+    hash ^= pieces_hash(white, &WHITE_KEYS);
+    hash ^= pieces_hash(black, &BLACK_KEYS);
+
+    hash
+}
+
+/// Returns the XOR of the keys of all squares in the given bitboard.
+pub fn pieces_hash(mut pieces: u64, keys: &[u64; SQUARES]) -> u64 {
+    let mut hash = 0;
+    while pieces != 0 {
+        hash ^= keys[pieces.trailing_zeros() as usize];
+        pieces &= pieces - 1;
+    }
+
+    hash
+}
+
+/// A small pseudo-random generator, evaluated at compile time.
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys(seed: u64) -> [u64; SQUARES] {
+    let mut keys = [0; SQUARES];
+    let mut i = 0;
+    while i < SQUARES {
+        keys[i] = splitmix64(seed + i as u64);
+        i += 1;
+    }
+
+    keys
+}
+
+const fn combine_keys(fst: &[u64; SQUARES], snd: &[u64; SQUARES]) -> [u64; SQUARES] {
+    let mut keys = [0; SQUARES];
+    let mut i = 0;
+    while i < SQUARES {
+        keys[i] = fst[i] ^ snd[i];
+        i += 1;
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::OthelloPosition;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[test]
+    fn incremental_hash_matches_the_hash_from_scratch() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let mut board = OthelloPosition::start();
+            while let Some((_, child)) = board.generate_children().choose(&mut rng) {
+                board = child.clone();
+                assert_eq!(
+                    board.hash(),
+                    hash(board.white(), board.black(), board.max_player()),
+                    "{}",
+                    board
+                );
+            }
+        }
+    }
+}
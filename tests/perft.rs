@@ -0,0 +1,57 @@
+use othello::{divide, perft, OthelloPosition};
+
+/// The number of move sequences of each length from the starting position,
+/// starting with length 1.
+const START_COUNTS: [u64; 9] = [4, 12, 56, 244, 1396, 8200, 55092, 390216, 3005288];
+
+#[test]
+fn perft_from_start_matches_known_counts() {
+    let board = OthelloPosition::start();
+    for (depth, &expected) in START_COUNTS.iter().enumerate() {
+        assert_eq!(perft(&board, depth as u32 + 1), expected, "depth {}", depth + 1);
+    }
+}
+
+#[test]
+fn perft_of_depth_zero_is_one() {
+    assert_eq!(perft(&OthelloPosition::start(), 0), 1);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let board = OthelloPosition::start();
+    let counts = divide(&board, 5);
+    assert_eq!(counts.len(), 4);
+    assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), perft(&board, 5));
+}
+
+#[test]
+fn symmetric_opening_moves_have_equal_counts() {
+    // The four opening moves are equivalent under the symmetries of the board.
+    let counts = divide(&OthelloPosition::start(), 6);
+    assert!(counts.iter().all(|(_, count)| *count == counts[0].1));
+}
+
+#[test]
+fn pass_counts_as_a_move() {
+    // White has no moves, so the only child is black to go after a pass.
+    let board: OthelloPosition =
+        "WXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXOEEEEEE"
+            .parse()
+            .unwrap();
+    let counts = divide(&board, 2);
+    assert_eq!(counts.len(), 1);
+    assert!(counts[0].0.is_pass());
+    assert_eq!(perft(&board, 1), 1);
+    assert_eq!(perft(&board, 2), perft(&board.pass(), 1));
+}
+
+#[test]
+fn finished_game_has_no_moves() {
+    let board: OthelloPosition =
+        "BXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"
+            .parse()
+            .unwrap();
+    assert_eq!(perft(&board, 1), 0);
+    assert_eq!(perft(&board, 3), 0);
+}